@@ -0,0 +1,113 @@
+// Copyright 2022-2022 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+#![allow(unused)]
+
+use std::time::Duration;
+
+use tao::{
+    event::Event,
+    event_loop::{ControlFlow, EventLoopBuilder},
+};
+use tray_icon::{
+    menu::{Menu, MenuEvent, MenuItem},
+    TrayIconBuilder,
+};
+
+enum UserEvent {
+    MenuEvent(tray_icon::menu::MenuEvent),
+    TaskDone,
+}
+
+fn main() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/icon.png");
+
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+
+    // set a menu event handler that forwards the event and wakes up the event loop
+    let proxy = event_loop.create_proxy();
+    MenuEvent::set_event_handler(Some(move |event| {
+        proxy.send_event(UserEvent::MenuEvent(event));
+    }));
+
+    let tray_menu = Menu::new();
+    let start_i = MenuItem::new("Start task", true, None);
+    let quit_i = MenuItem::new("Quit", true, None);
+    tray_menu.append_items(&[&start_i, &quit_i]);
+
+    let mut tray_icon = None;
+    let mut frames = Vec::new();
+    let task_proxy = event_loop.create_proxy();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::NewEvents(tao::event::StartCause::Init) => {
+                let icon = load_icon(std::path::Path::new(path));
+
+                // the frames of the animation, the icon pulsing through a few shades
+                frames = [255, 200, 150, 100, 150, 200]
+                    .into_iter()
+                    .map(|shade| icon.tinted([shade, shade, 255]).unwrap())
+                    .collect();
+
+                // We create the icon once the event loop is actually running
+                // to prevent issues like https://github.com/tauri-apps/tray-icon/issues/90
+                tray_icon = Some(
+                    TrayIconBuilder::new()
+                        .with_menu(Box::new(tray_menu.clone()))
+                        .with_tooltip("Idle")
+                        .with_icon(icon)
+                        .build()
+                        .unwrap(),
+                );
+            }
+
+            Event::UserEvent(UserEvent::MenuEvent(event)) => {
+                if event.id == start_i.id() {
+                    if let Some(tray_icon) = &tray_icon {
+                        start_i.set_enabled(false);
+                        let _ = tray_icon.set_tooltip(Some("Working..."));
+                        tray_icon.set_animation(frames.clone(), Duration::from_millis(100));
+                    }
+
+                    // run the task in the background and wake up the event loop once it is done
+                    let proxy = task_proxy.clone();
+                    std::thread::spawn(move || {
+                        std::thread::sleep(Duration::from_secs(5));
+                        proxy.send_event(UserEvent::TaskDone);
+                    });
+                }
+
+                if event.id == quit_i.id() {
+                    tray_icon.take();
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+
+            Event::UserEvent(UserEvent::TaskDone) => {
+                if let Some(tray_icon) = &tray_icon {
+                    tray_icon.stop_animation();
+                    let _ = tray_icon.set_tooltip(Some("Idle"));
+                }
+                start_i.set_enabled(true);
+            }
+
+            _ => {}
+        }
+    })
+}
+
+fn load_icon(path: &std::path::Path) -> tray_icon::Icon {
+    let (icon_rgba, icon_width, icon_height) = {
+        let image = image::open(path)
+            .expect("Failed to open icon path")
+            .into_rgba8();
+        let (width, height) = image.dimensions();
+        let rgba = image.into_raw();
+        (rgba, width, height)
+    };
+    tray_icon::Icon::from_rgba(icon_rgba, icon_width, icon_height).expect("Failed to open icon")
+}
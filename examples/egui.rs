@@ -4,7 +4,16 @@
 use std::{cell::RefCell, rc::Rc};
 
 use eframe::egui;
-use tray_icon::TrayIconBuilder;
+use tray_icon::{
+    menu::{Menu, MenuItem},
+    TrayIconBuilder,
+};
+
+const QUIT_ID: &str = "quit";
+
+fn tray_menu() -> Menu {
+    Menu::with_items(&[&MenuItem::with_id(QUIT_ID, "Quit", true, None)]).unwrap()
+}
 
 fn main() -> Result<(), eframe::Error> {
     let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/icon.png");
@@ -15,11 +24,9 @@ fn main() -> Result<(), eframe::Error> {
     // where we initialize gtk and create the tray_icon
     #[cfg(target_os = "linux")]
     std::thread::spawn(|| {
-        use tray_icon::menu::Menu;
-
         gtk::init().unwrap();
         let _tray_icon = TrayIconBuilder::new()
-            .with_menu(Box::new(Menu::new()))
+            .with_menu(Box::new(tray_menu()))
             .with_icon(icon)
             .build()
             .unwrap();
@@ -38,9 +45,13 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(move |_cc| {
             #[cfg(not(target_os = "linux"))]
             {
-                tray_c
-                    .borrow_mut()
-                    .replace(TrayIconBuilder::new().with_icon(icon).build().unwrap());
+                tray_c.borrow_mut().replace(
+                    TrayIconBuilder::new()
+                        .with_menu(Box::new(tray_menu()))
+                        .with_icon(icon)
+                        .build()
+                        .unwrap(),
+                );
             }
             Box::<MyApp>::default()
         }),
@@ -63,12 +74,20 @@ impl Default for MyApp {
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        use tray_icon::TrayIconEvent;
+        use tray_icon::{menu::MenuEvent, TrayIcon, TrayIconEvent};
 
         if let Ok(event) = TrayIconEvent::receiver().try_recv() {
             println!("tray event: {event:?}");
         }
 
+        if let Ok(event) = MenuEvent::receiver().try_recv() {
+            // the tray icon whose menu the item was clicked in
+            println!("menu event: {event:?} from {:?}", TrayIcon::menu_owner_id());
+            if event.id == QUIT_ID {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("My egui Application");
             ui.horizontal(|ui| {
@@ -15,6 +15,20 @@ pub enum Error {
     PngEncodingError(#[from] png::EncodingError),
     #[error("not on the main thread")]
     NotMainThread,
+    #[error(transparent)]
+    BadIcon(#[from] crate::BadIcon),
+    #[error("tooltip is {len} UTF-16 code units long, the maximum is {max}")]
+    TooltipTooLong { len: usize, max: usize },
+    #[error("the tray icon was added but not shown in time")]
+    NotShown,
+    #[error("the tray icon has no menu")]
+    NoMenu,
+    #[error("this operation is not supported on this platform")]
+    NotSupported,
+    #[error(transparent)]
+    WindowCreationFailed(std::io::Error),
+    #[error(transparent)]
+    IconRegistrationFailed(std::io::Error),
 }
 
 /// Convenient type alias of Result type for tray-icon.
@@ -4,8 +4,12 @@
 
 // taken from https://github.com/rust-windowing/winit/blob/92fdf5ba85f920262a61cee4590f4a11ad5738d1/src/icon.rs
 
+#[cfg(feature = "icon-decoding")]
+mod decode;
+
 use crate::platform_impl::PlatformIcon;
-use std::{error::Error, fmt, io, mem};
+use once_cell::sync::Lazy;
+use std::{collections::VecDeque, error::Error, fmt, io, mem, sync::Mutex};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -18,8 +22,14 @@ pub(crate) struct Pixel {
 
 pub(crate) const PIXEL_SIZE: usize = mem::size_of::<Pixel>();
 
+/// The largest width or height accepted for an icon.
+pub const MAX_ICON_DIMENSION: u32 = 4096;
+
 #[derive(Debug)]
-/// An error produced when using [`Icon::from_rgba`] with invalid arguments.
+/// An error produced when creating an [`Icon`] from invalid data.
+///
+/// None of the [`Icon`] constructors panic on bad input, they return this error instead.
+#[non_exhaustive]
 pub enum BadIcon {
     /// Produced when the length of the `rgba` argument isn't divisible by 4, thus `rgba` can't be
     /// safely interpreted as 32bpp RGBA pixels.
@@ -32,8 +42,24 @@ pub enum BadIcon {
         width_x_height: usize,
         pixel_count: usize,
     },
+    /// Produced when the lengths of the `rgb` and `alpha` arguments of [`Icon::from_rgb_and_alpha`]
+    /// don't match `width * height * 3` and `width * height` bytes.
+    PlaneLengthMismatch {
+        width: u32,
+        height: u32,
+        rgb_len: usize,
+        alpha_len: usize,
+    },
+    /// Produced when the icon's width or height exceeds [`MAX_ICON_DIMENSION`].
+    TooLarge { width: u32, height: u32 },
+    /// Produced when the icon's width or height is zero.
+    ZeroSized { width: u32, height: u32 },
     /// Produced when underlying OS functionality failed to create the icon
     OsError(io::Error),
+    /// Produced when the icon data couldn't be decoded, e.g. a malformed data URI or PNG.
+    DecodeFailed(String),
+    /// Produced when the icon data is in a format that isn't supported.
+    UnsupportedFormat(String),
 }
 
 impl fmt::Display for BadIcon {
@@ -52,18 +78,43 @@ impl fmt::Display for BadIcon {
                 "The specified dimensions ({:?}x{:?}) don't match the number of pixels supplied by the `rgba` argument ({:?}). For those dimensions, the expected pixel count is {:?}.",
                 width, height, pixel_count, width_x_height,
             ),
+            BadIcon::PlaneLengthMismatch {
+                width,
+                height,
+                rgb_len,
+                alpha_len,
+            } => write!(f,
+                "The lengths of the `rgb` ({:?}) and `alpha` ({:?}) arguments don't match the specified dimensions ({:?}x{:?}). For those dimensions, the expected lengths are {:?} and {:?}.",
+                rgb_len, alpha_len, width, height, *width as usize * *height as usize * 3, *width as usize * *height as usize,
+            ),
+            BadIcon::TooLarge { width, height } => write!(f,
+                "The specified dimensions ({:?}x{:?}) exceed the maximum icon dimension of {:?}.",
+                width, height, MAX_ICON_DIMENSION,
+            ),
+            BadIcon::ZeroSized { width, height } => write!(
+                f,
+                "The specified dimensions ({:?}x{:?}) are empty, an icon needs at least one pixel.",
+                width, height,
+            ),
             BadIcon::OsError(e) => write!(f, "OS error when instantiating the icon: {:?}", e),
+            BadIcon::DecodeFailed(e) => write!(f, "Failed to decode the icon data: {}", e),
+            BadIcon::UnsupportedFormat(format) => {
+                write!(f, "Unsupported icon format: {}", format)
+            }
         }
     }
 }
 
 impl Error for BadIcon {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(self)
+        match self {
+            BadIcon::OsError(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct RgbaIcon {
     pub(crate) rgba: Vec<u8>,
     pub(crate) width: u32,
@@ -80,17 +131,19 @@ mod constructors {
 
     impl RgbaIcon {
         pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+            check_dimensions(width, height)?;
             if rgba.len() % PIXEL_SIZE != 0 {
                 return Err(BadIcon::ByteCountNotDivisibleBy4 {
                     byte_count: rgba.len(),
                 });
             }
             let pixel_count = rgba.len() / PIXEL_SIZE;
-            if pixel_count != (width * height) as usize {
+            let width_x_height = width as usize * height as usize;
+            if pixel_count != width_x_height {
                 Err(BadIcon::DimensionsVsPixelCount {
                     width,
                     height,
-                    width_x_height: (width * height) as usize,
+                    width_x_height,
                     pixel_count,
                 })
             } else {
@@ -103,6 +156,95 @@ mod constructors {
         }
     }
 
+    impl RgbaIcon {
+        /// Multiplies the RGB channels of every pixel by `color`, leaving alpha untouched.
+        pub fn tint(&mut self, color: [u8; 3]) {
+            for pixel in self.rgba.chunks_exact_mut(PIXEL_SIZE) {
+                for (channel, tint) in pixel.iter_mut().zip(color) {
+                    *channel = (*channel as u16 * tint as u16 / u8::MAX as u16) as u8;
+                }
+            }
+        }
+
+        /// Converts premultiplied alpha pixels to the straight alpha they are expected in.
+        ///
+        /// Fully transparent pixels lose their color, which premultiplying erased anyway.
+        pub fn unpremultiply(&mut self) {
+            for pixel in self.rgba.chunks_exact_mut(PIXEL_SIZE) {
+                let a = pixel[3] as u16;
+                for channel in &mut pixel[..3] {
+                    *channel = match a {
+                        0 => 0,
+                        // round to the nearest value, and clamp invalid channels larger than alpha
+                        _ => ((*channel as u16 * u8::MAX as u16 + a / 2) / a).min(u8::MAX as u16)
+                            as u8,
+                    };
+                }
+            }
+        }
+
+        /// Decodes a PNG image into 32bpp RGBA pixels.
+        #[cfg(feature = "icon-decoding")]
+        pub fn from_png(bytes: &[u8]) -> Result<Self, BadIcon> {
+            let decode_failed = |e: png::DecodingError| BadIcon::DecodeFailed(e.to_string());
+
+            let mut decoder = png::Decoder::new(bytes);
+            decoder.set_transformations(png::Transformations::normalize_to_color8());
+            let mut reader = decoder.read_info().map_err(decode_failed)?;
+            // check before allocating the output buffer
+            check_dimensions(reader.info().width, reader.info().height)?;
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut buf).map_err(decode_failed)?;
+            buf.truncate(info.buffer_size());
+
+            let rgba = match info.color_type {
+                png::ColorType::Rgba => buf,
+                png::ColorType::Rgb => buf
+                    .chunks_exact(3)
+                    .flat_map(|p| [p[0], p[1], p[2], u8::MAX])
+                    .collect(),
+                png::ColorType::GrayscaleAlpha => buf
+                    .chunks_exact(2)
+                    .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                    .collect(),
+                png::ColorType::Grayscale => {
+                    buf.into_iter().flat_map(|p| [p, p, p, u8::MAX]).collect()
+                }
+                // expanded to RGB(A) by `normalize_to_color8`
+                png::ColorType::Indexed => {
+                    return Err(BadIcon::UnsupportedFormat("indexed PNG".into()))
+                }
+            };
+
+            RgbaIcon::from_rgba(rgba, info.width, info.height)
+        }
+
+        pub fn from_rgb_and_alpha(
+            rgb: &[u8],
+            alpha: &[u8],
+            width: u32,
+            height: u32,
+        ) -> Result<Self, BadIcon> {
+            check_dimensions(width, height)?;
+            let pixel_count = width as usize * height as usize;
+            if rgb.len() != pixel_count * 3 || alpha.len() != pixel_count {
+                return Err(BadIcon::PlaneLengthMismatch {
+                    width,
+                    height,
+                    rgb_len: rgb.len(),
+                    alpha_len: alpha.len(),
+                });
+            }
+
+            let rgba = rgb
+                .chunks_exact(3)
+                .zip(alpha)
+                .flat_map(|(rgb, &a)| [rgb[0], rgb[1], rgb[2], a])
+                .collect();
+            RgbaIcon::from_rgba(rgba, width, height)
+        }
+    }
+
     impl NoIcon {
         pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
             // Create the rgba icon anyway to validate the input
@@ -112,6 +254,86 @@ mod constructors {
     }
 }
 
+fn check_dimensions(width: u32, height: u32) -> Result<(), BadIcon> {
+    if width == 0 || height == 0 {
+        return Err(BadIcon::ZeroSized { width, height });
+    }
+    if width > MAX_ICON_DIMENSION || height > MAX_ICON_DIMENSION {
+        return Err(BadIcon::TooLarge { width, height });
+    }
+    Ok(())
+}
+
+/// Parses a `data:<mime>;base64,<data>` URI, returning the MIME type and the decoded bytes.
+#[cfg(feature = "icon-decoding")]
+fn parse_data_uri(uri: &str) -> Result<(&str, Vec<u8>), BadIcon> {
+    let malformed = || BadIcon::DecodeFailed("malformed data URI".into());
+
+    let rest = uri
+        .get(..5)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("data:"))
+        .map(|_| &uri[5..])
+        .ok_or_else(malformed)?;
+    let (header, data) = rest.split_once(',').ok_or_else(malformed)?;
+
+    let mut params = header.split(';');
+    let mime = params.next().unwrap_or_default();
+    if !params.any(|p| p.eq_ignore_ascii_case("base64")) {
+        return Err(BadIcon::DecodeFailed(
+            "only base64 encoded data URIs are supported".into(),
+        ));
+    }
+
+    Ok((mime, decode_base64(data).ok_or_else(malformed)?))
+}
+
+/// Decodes standard base64, ignoring whitespace and padding.
+#[cfg(feature = "icon-decoding")]
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in data
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        acc = (acc << 6) | value(c)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    Some(out)
+}
+
+/// How many tinted icons [`Icon::tinted`] keeps, the least recently used one is dropped first.
+const TINTED_ICONS_CAPACITY: usize = 16;
+
+/// Recently tinted icons, most recently used first, so repeatedly tinting the same icon
+/// doesn't read back its pixels or recreate the platform icon.
+static TINTED_ICONS: Lazy<Mutex<VecDeque<TintedIcon>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+struct TintedIcon {
+    /// Kept alive so its platform icon isn't freed and reused by another icon while cached.
+    source: Icon,
+    color: [u8; 3],
+    icon: Icon,
+}
+
 /// An icon used for the window titlebar, taskbar, etc.
 #[derive(Clone)]
 pub struct Icon {
@@ -128,26 +350,185 @@ impl Icon {
     /// Creates an icon from 32bpp RGBA data.
     ///
     /// The length of `rgba` must be divisible by 4, and `width * height` must equal
-    /// `rgba.len() / 4`. Otherwise, this will return a `BadIcon` error.
+    /// `rgba.len() / 4`. Otherwise, this will return a `BadIcon` error, as it will if
+    /// `width` or `height` is zero or exceeds [`MAX_ICON_DIMENSION`].
+    ///
+    /// The pixels must have straight alpha, as decoded from PNG files. Semi-transparent pixels
+    /// with premultiplied alpha, e.g. rendered by a 2D graphics library, are drawn too dark
+    /// and give soft edges a dark halo, use [`Icon::from_premultiplied_rgba`] for those.
     pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
         Ok(Icon {
             inner: PlatformIcon::from_rgba(rgba, width, height)?,
         })
     }
 
-    /// Create an icon from a file path.
+    /// Creates an icon from 32bpp RGBA data with premultiplied alpha, i.e. with the color
+    /// channels of every pixel already multiplied by its alpha.
     ///
-    /// Specify `size` to load a specific icon size from the file, or `None` to load the default
-    /// icon size from the file.
+    /// The pixels are converted to straight alpha first, see [`Icon::from_rgba`].
+    pub fn from_premultiplied_rgba(
+        rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, BadIcon> {
+        let mut rgba = RgbaIcon::from_rgba(rgba, width, height)?;
+        rgba.unpremultiply();
+        Icon::from_rgba(rgba.rgba, rgba.width, rgba.height)
+    }
+
+    /// Creates an icon from separate 24bpp RGB and 8bpp alpha planes, e.g. produced by a video
+    /// or GPU pipeline.
     ///
-    /// In cases where the specified size does not exist in the file, Windows may perform scaling
-    /// to get an icon of the desired size.
+    /// `rgb` must be `width * height * 3` bytes long and `alpha` `width * height` bytes long.
+    /// Otherwise, this will return a [`BadIcon::PlaneLengthMismatch`] error.
+    pub fn from_rgb_and_alpha(
+        rgb: &[u8],
+        alpha: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Self, BadIcon> {
+        let rgba = RgbaIcon::from_rgb_and_alpha(rgb, alpha, width, height)?;
+        Icon::from_rgba(rgba.rgba, rgba.width, rgba.height)
+    }
+
+    /// Creates an icon from the contents of a PNG, ICO or BMP file, detecting the format
+    /// from its signature.
+    ///
+    /// The largest image of an ICO file is used. Only uncompressed 24 and 32 bpp bitmaps
+    /// are supported in ICO and BMP files.
+    #[cfg(feature = "icon-decoding")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BadIcon> {
+        let rgba = decode::decode(bytes)?;
+        Icon::from_rgba(rgba.rgba, rgba.width, rgba.height)
+    }
+
+    /// Creates an icon from a base64 encoded data URI, e.g. `data:image/png;base64,iVBORw0KGgo...`.
+    ///
+    /// This is useful for apps that store their icons in config files.
+    /// Only `image/png` is currently supported, other MIME types return [`BadIcon::UnsupportedFormat`].
+    #[cfg(feature = "icon-decoding")]
+    pub fn from_data_uri(uri: &str) -> Result<Self, BadIcon> {
+        let (mime, bytes) = parse_data_uri(uri)?;
+        if !mime.eq_ignore_ascii_case("image/png") {
+            return Err(BadIcon::UnsupportedFormat(mime.to_string()));
+        }
+
+        let rgba = RgbaIcon::from_png(&bytes)?;
+        Icon::from_rgba(rgba.rgba, rgba.width, rgba.height)
+    }
+
+    /// Creates a new icon by multiplying the RGB channels of this icon by `color`
+    /// while preserving its alpha channel.
+    ///
+    /// This is useful for status indicators that need the same glyph in several colors,
+    /// e.g. a white glyph tinted with `[0, 255, 0]` becomes green.
+    ///
+    /// The 16 most recently tinted icons are cached per source icon and color, so calling
+    /// this repeatedly on the same icon, or a clone of it, with the same color doesn't
+    /// read back its pixels.
+    pub fn tinted(&self, color: [u8; 3]) -> Result<Self, BadIcon> {
+        // the cache is always left in a consistent state, so a poisoned lock is fine to reuse
+        let mut cache = TINTED_ICONS.lock().unwrap_or_else(|e| e.into_inner());
+        let hit = cache
+            .iter()
+            .position(|tinted| tinted.color == color && tinted.source.inner.is_same(&self.inner));
+        if let Some(tinted) = hit.and_then(|i| cache.remove(i)) {
+            let icon = tinted.icon.clone();
+            cache.push_front(tinted);
+            return Ok(icon);
+        }
+
+        let mut rgba = self.inner.to_rgba()?;
+        rgba.tint(color);
+        let icon = Icon::from_rgba(rgba.rgba, rgba.width, rgba.height)?;
+        cache.push_front(TintedIcon {
+            source: self.clone(),
+            color,
+            icon: icon.clone(),
+        });
+        cache.truncate(TINTED_ICONS_CAPACITY);
+        Ok(icon)
+    }
+
+    /// Reads back the 32bpp RGBA pixels of this icon, with its width and height. **Windows only**.
+    ///
+    /// The pixels are read from the created `HICON`, so this is useful to compare the result
+    /// of drawing features like [`Icon::tinted`] against expected buffers in tests.
     #[cfg(windows)]
+    pub fn to_rgba_snapshot(&self) -> Result<(Vec<u8>, u32, u32), BadIcon> {
+        let rgba = self.inner.to_rgba()?;
+        Ok((rgba.rgba, rgba.width, rgba.height))
+    }
+
+    /// Create an icon from a PNG, ICO or BMP file, see [`Icon::from_bytes`].
+    ///
+    /// Returns [`BadIcon::OsError`] if the file can't be read and [`BadIcon::UnsupportedFormat`]
+    /// if it isn't one of these formats.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** ICO files are loaded with `LoadImageW`, so specify `size` to load
+    ///   a specific icon size from the file, or `None` to load the default icon size.
+    ///   In cases where the specified size does not exist in the file, Windows may perform
+    ///   scaling to get an icon of the desired size. Other files, and ICO files that
+    ///   `LoadImageW` fails to load, are decoded instead. Without the `icon-decoding` feature,
+    ///   every file is loaded with `LoadImageW`.
+    /// - **macOS / Linux:** `size` is ignored, the icon is scaled by the system.
+    ///   Requires the `icon-decoding` feature.
+    #[cfg(any(windows, feature = "icon-decoding"))]
     pub fn from_path<P: AsRef<std::path::Path>>(
         path: P,
         size: Option<(u32, u32)>,
     ) -> Result<Self, BadIcon> {
-        let win_icon = PlatformIcon::from_path(path, size)?;
+        #[cfg(not(feature = "icon-decoding"))]
+        let icon = PlatformIcon::from_path(path, size).map(|inner| Icon { inner });
+        #[cfg(feature = "icon-decoding")]
+        let icon = Icon::decode_path(path.as_ref(), size);
+        icon
+    }
+
+    /// Loads the file at `path`, see [`Icon::from_path`].
+    #[cfg(feature = "icon-decoding")]
+    fn decode_path(path: &std::path::Path, size: Option<(u32, u32)>) -> Result<Self, BadIcon> {
+        #[cfg(windows)]
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ico"))
+        {
+            if let Ok(win_icon) = PlatformIcon::from_path(path, size) {
+                return Ok(Icon { inner: win_icon });
+            }
+        }
+        #[cfg(not(windows))]
+        let _ = size;
+
+        let bytes = std::fs::read(path).map_err(|e| {
+            BadIcon::OsError(io::Error::new(
+                e.kind(),
+                format!("failed to read {}: {e}", path.display()),
+            ))
+        })?;
+        Icon::from_bytes(&bytes)
+    }
+
+    /// Create an icon from a file path, sized for the notification area at the
+    /// current system DPI.
+    ///
+    /// This queries the DPI of the primary monitor and loads the icon at the matching
+    /// small icon size, e.g. 16x16 at 100% scale and 24x24 at 150% scale,
+    /// letting Windows scale the closest size in the file if there isn't an exact match.
+    #[cfg(windows)]
+    pub fn from_path_for_current_dpi<P: AsRef<std::path::Path>>(path: P) -> Result<Self, BadIcon> {
+        Self::from_path_for_dpi(path, crate::platform_impl::system_dpi())
+    }
+
+    /// Same as [`Icon::from_path_for_current_dpi`] but for an explicit `dpi`, where 96 is 100% scale.
+    #[cfg(windows)]
+    pub fn from_path_for_dpi<P: AsRef<std::path::Path>>(
+        path: P,
+        dpi: u32,
+    ) -> Result<Self, BadIcon> {
+        let win_icon = PlatformIcon::from_path_for_dpi(path, dpi)?;
         Ok(Icon { inner: win_icon })
     }
 
@@ -158,6 +539,8 @@ impl Icon {
     ///
     /// In cases where the specified size does not exist in the file, Windows may perform scaling
     /// to get an icon of the desired size.
+    ///
+    /// Every call loads a new copy of the icon, which is destroyed when the last clone is dropped.
     #[cfg(windows)]
     pub fn from_resource(ordinal: u16, size: Option<(u32, u32)>) -> Result<Self, BadIcon> {
         let win_icon = PlatformIcon::from_resource(ordinal, size)?;
@@ -176,9 +559,189 @@ impl Icon {
     }
 
     /// Create an icon from an HICON
+    ///
+    /// The icon takes ownership of the handle and destroys it when the last clone is dropped,
+    /// so it mustn't be a shared icon, e.g. one loaded with `LoadIconW` or `LR_SHARED`,
+    /// and it mustn't be destroyed elsewhere. Use [`Icon::from_shared_handle`] for those.
+    ///
+    /// The handle must be a valid icon or cursor handle, otherwise setting the icon fails.
     #[cfg(windows)]
     pub fn from_handle(handle: isize) -> Self {
         let win_icon = PlatformIcon::from_handle(handle as _);
         Icon { inner: win_icon }
     }
+
+    /// Create an icon from a copy of an HICON the caller keeps owning, e.g. one loaded
+    /// with `LoadIconW` or generated with GDI for every frame of an animation.
+    ///
+    /// The handle can be destroyed as soon as this returns.
+    #[cfg(windows)]
+    pub fn from_shared_handle(handle: isize) -> Result<Self, BadIcon> {
+        let win_icon = PlatformIcon::from_shared_handle(handle as _)?;
+        Ok(Icon { inner: win_icon })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_rgba_rejects_mismatched_length() {
+        assert!(matches!(
+            RgbaIcon::from_rgba(vec![0; 4 * 3], 2, 2),
+            Err(BadIcon::DimensionsVsPixelCount {
+                width_x_height: 4,
+                pixel_count: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            RgbaIcon::from_rgba(vec![0; 7], 1, 2),
+            Err(BadIcon::ByteCountNotDivisibleBy4 { byte_count: 7 })
+        ));
+        assert!(RgbaIcon::from_rgba(vec![0; 4 * 4], 2, 2).is_ok());
+    }
+
+    #[test]
+    fn from_rgba_rejects_too_large() {
+        let side = MAX_ICON_DIMENSION + 1;
+        assert!(matches!(
+            RgbaIcon::from_rgba(vec![0; side as usize * 4], side, 1),
+            Err(BadIcon::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn from_rgba_rejects_zero_size() {
+        assert!(matches!(
+            RgbaIcon::from_rgba(Vec::new(), 0, 0),
+            Err(BadIcon::ZeroSized {
+                width: 0,
+                height: 0
+            })
+        ));
+        assert!(matches!(
+            RgbaIcon::from_rgba(Vec::new(), 16, 0),
+            Err(BadIcon::ZeroSized { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "icon-decoding")]
+    fn from_path_reports_missing_and_unsupported_files() {
+        let dir = std::env::temp_dir();
+
+        let missing = dir.join("tray-icon-missing-icon.png");
+        assert!(matches!(
+            Icon::from_path(&missing, None),
+            Err(BadIcon::OsError(e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+
+        let gif = dir.join("tray-icon-unsupported-icon.gif");
+        std::fs::write(&gif, b"GIF89a").unwrap();
+        let result = Icon::from_path(&gif, None);
+        std::fs::remove_file(&gif).unwrap();
+        assert!(matches!(result, Err(BadIcon::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn from_rgb_and_alpha_interleaves_planes() {
+        let icon = RgbaIcon::from_rgb_and_alpha(&[1, 2, 3, 4, 5, 6], &[7, 8], 2, 1).unwrap();
+        assert_eq!(icon.rgba, vec![1, 2, 3, 7, 4, 5, 6, 8]);
+
+        assert!(matches!(
+            RgbaIcon::from_rgb_and_alpha(&[0; 6], &[0; 1], 2, 1),
+            Err(BadIcon::PlaneLengthMismatch {
+                rgb_len: 6,
+                alpha_len: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "icon-decoding")]
+    fn parses_data_uri() {
+        let (mime, bytes) = parse_data_uri("data:image/png;base64,dHJheS1pY29u").unwrap();
+        assert_eq!(mime, "image/png");
+        assert_eq!(bytes, b"tray-icon");
+
+        let (_, bytes) = parse_data_uri("DATA:image/png;base64,dHJheQ==").unwrap();
+        assert_eq!(bytes, b"tray");
+
+        assert!(parse_data_uri("image/png;base64,dHJheQ==").is_err());
+        assert!(parse_data_uri("data:image/png,dHJheQ==").is_err());
+        assert!(parse_data_uri("data:image/png;base64,dHJ*eQ==").is_err());
+    }
+
+    #[test]
+    fn unpremultiply_restores_straight_alpha() {
+        let mut icon = RgbaIcon::from_rgba(
+            vec![
+                128, 64, 0, 128, // half transparent
+                10, 20, 30, 255, // opaque, unchanged
+                5, 5, 5, 0, // fully transparent
+                200, 1, 2, 100, // invalid channel larger than alpha
+            ],
+            4,
+            1,
+        )
+        .unwrap();
+        icon.unpremultiply();
+        assert_eq!(
+            icon.rgba,
+            vec![255, 128, 0, 128, 10, 20, 30, 255, 0, 0, 0, 0, 255, 3, 5, 100]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "icon-decoding")]
+    fn unpremultiply_round_trips_soft_shadow() {
+        // a soft shadow survives a round trip through premultiplied alpha, up to the
+        // precision lost by premultiplying
+        let straight = RgbaIcon::from_png(include_bytes!("../examples/soft-shadow.png")).unwrap();
+        let mut icon = straight.clone();
+        for pixel in icon.rgba.chunks_exact_mut(PIXEL_SIZE) {
+            let a = pixel[3] as u16;
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u16 * a + 127) / 255) as u8;
+            }
+        }
+        icon.unpremultiply();
+        for (expected, actual) in straight.rgba.chunks(4).zip(icon.rgba.chunks(4)) {
+            let a = expected[3] as i32;
+            let tolerance = if a == 0 { 255 } else { 255 / (2 * a) + 1 };
+            for (e, c) in expected[..3].iter().zip(&actual[..3]) {
+                assert!((*e as i32 - *c as i32).abs() <= tolerance);
+            }
+            assert_eq!(expected[3], actual[3]);
+        }
+    }
+
+    #[test]
+    fn tinted_icons_cache_is_bounded() {
+        let icon = Icon::from_rgba(vec![255, 255, 255, 255], 1, 1).unwrap();
+        for shade in 0..TINTED_ICONS_CAPACITY as u8 * 2 {
+            icon.tinted([shade, 0, 0]).unwrap();
+        }
+        assert!(TINTED_ICONS.lock().unwrap().len() <= TINTED_ICONS_CAPACITY);
+    }
+
+    #[test]
+    fn tinting_a_clone_reuses_the_cached_icon() {
+        let icon = Icon::from_rgba(vec![255, 255, 255, 255], 1, 1).unwrap();
+        let tinted = icon.tinted([1, 2, 3]).unwrap();
+        let again = icon.clone().tinted([1, 2, 3]).unwrap();
+        assert!(tinted.inner.is_same(&again.inner));
+        assert_eq!(again.inner.to_rgba().unwrap().rgba, vec![1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn tint_preserves_alpha() {
+        let mut icon =
+            RgbaIcon::from_rgba(vec![255, 255, 255, 128, 100, 50, 0, 255], 2, 1).unwrap();
+        icon.tint([0, 255, 128]);
+        assert_eq!(icon.rgba, vec![0, 255, 128, 128, 0, 50, 0, 255]);
+    }
 }
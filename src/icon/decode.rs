@@ -0,0 +1,241 @@
+// Copyright 2022-2022 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Decoders for the image formats accepted by [`Icon::from_bytes`](super::Icon::from_bytes).
+
+use super::{check_dimensions, BadIcon, RgbaIcon};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const ICO_SIGNATURE: &[u8] = &[0, 0, 1, 0];
+const BMP_SIGNATURE: &[u8] = b"BM";
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+/// Decodes a PNG, ICO or BMP image, detected from its signature.
+pub(crate) fn decode(bytes: &[u8]) -> Result<RgbaIcon, BadIcon> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        RgbaIcon::from_png(bytes)
+    } else if bytes.starts_with(ICO_SIGNATURE) {
+        decode_ico(bytes)
+    } else if bytes.starts_with(BMP_SIGNATURE) {
+        decode_bmp(bytes)
+    } else {
+        Err(BadIcon::UnsupportedFormat(
+            "unknown image format, expected PNG, ICO or BMP".into(),
+        ))
+    }
+}
+
+fn malformed(format: &str) -> BadIcon {
+    BadIcon::DecodeFailed(format!("malformed {format} data"))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn i32_at(bytes: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn decode_bmp(bytes: &[u8]) -> Result<RgbaIcon, BadIcon> {
+    let malformed = || malformed("BMP");
+
+    let offset = u32_at(bytes, 10).ok_or_else(malformed)? as usize;
+    let header = bytes.get(14..).ok_or_else(malformed)?;
+    let pixels = bytes.get(offset..).ok_or_else(malformed)?;
+
+    decode_dib(header, pixels, false, "BMP")
+}
+
+fn decode_ico(bytes: &[u8]) -> Result<RgbaIcon, BadIcon> {
+    let malformed = || malformed("ICO");
+
+    let count = u16_at(bytes, 4).ok_or_else(malformed)? as usize;
+    // use the largest image in the file
+    let entry = (0..count)
+        .filter_map(|i| bytes.get(6 + i * 16..6 + (i + 1) * 16))
+        .max_by_key(|entry| {
+            // a size of 0 means 256
+            let width = if entry[0] == 0 { 256 } else { entry[0] as u32 };
+            let height = if entry[1] == 0 { 256 } else { entry[1] as u32 };
+            (width * height, u16_at(entry, 6))
+        })
+        .ok_or_else(malformed)?;
+
+    let size = u32_at(entry, 8).ok_or_else(malformed)? as usize;
+    let offset = u32_at(entry, 12).ok_or_else(malformed)? as usize;
+    let data = offset
+        .checked_add(size)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(malformed)?;
+
+    // Windows Vista and later store large images as PNG
+    if data.starts_with(PNG_SIGNATURE) {
+        return RgbaIcon::from_png(data);
+    }
+
+    let header_size = u32_at(data, 0).ok_or_else(malformed)? as usize;
+    let compression = u32_at(data, 16).ok_or_else(malformed)?;
+    // the color masks follow a `BITMAPINFOHEADER` when using bitfields
+    let masks_size = if compression == BI_BITFIELDS && header_size == 40 {
+        12
+    } else {
+        0
+    };
+    let pixels = data.get(header_size + masks_size..).ok_or_else(malformed)?;
+
+    decode_dib(data, pixels, true, "ICO")
+}
+
+/// Decodes an uncompressed 24 or 32 bpp device-independent bitmap, where `header` starts with
+/// its `BITMAPINFOHEADER` and `pixels` with its pixel data.
+///
+/// Bitmaps in icons have their height doubled and are followed by a 1 bpp transparency mask.
+fn decode_dib(header: &[u8], pixels: &[u8], icon: bool, format: &str) -> Result<RgbaIcon, BadIcon> {
+    let malformed = || malformed(format);
+
+    let width = i32_at(header, 4).ok_or_else(malformed)?;
+    let height = i32_at(header, 8).ok_or_else(malformed)?;
+    let bpp = u16_at(header, 14).ok_or_else(malformed)?;
+    let compression = u32_at(header, 16).ok_or_else(malformed)?;
+
+    if !matches!(
+        (bpp, compression),
+        (24, BI_RGB) | (32, BI_RGB) | (32, BI_BITFIELDS)
+    ) {
+        return Err(BadIcon::UnsupportedFormat(format!(
+            "{bpp} bpp {format} with compression {compression}"
+        )));
+    }
+    if width <= 0 || height == 0 {
+        return Err(malformed());
+    }
+
+    // negative heights are used for top-down bitmaps
+    let top_down = height < 0;
+    let width = width as u32;
+    let height = if icon {
+        height.unsigned_abs() / 2
+    } else {
+        height.unsigned_abs()
+    };
+    check_dimensions(width, height)?;
+
+    let (width, height) = (width as usize, height as usize);
+    let bytes_per_pixel = bpp as usize / 8;
+    // rows are padded to 4 bytes
+    let stride = (width * bytes_per_pixel).div_ceil(4) * 4;
+    let mask_stride = width.div_ceil(32) * 4;
+
+    let color = pixels.get(..stride * height).ok_or_else(malformed)?;
+    let mask = if icon {
+        pixels.get(stride * height..stride * height + mask_stride * height)
+    } else {
+        None
+    };
+    let row_index = |y: usize| if top_down { y } else { height - 1 - y };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let row = &color[row_index(y) * stride..];
+        for pixel in row.chunks_exact(bytes_per_pixel).take(width) {
+            let alpha = if bytes_per_pixel == 4 { pixel[3] } else { 255 };
+            rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], alpha]);
+        }
+    }
+
+    // 24 bpp bitmaps have no alpha channel and 32 bpp ones often leave it unused,
+    // use the transparency mask of icons instead
+    let has_alpha = bytes_per_pixel == 4 && rgba.chunks_exact(4).any(|p| p[3] != 0);
+    if !has_alpha {
+        for (i, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let transparent = mask.is_some_and(|mask| {
+                mask[row_index(y) * mask_stride + x / 8] & (0x80 >> (x % 8)) != 0
+            });
+            pixel[3] = if transparent { 0 } else { 255 };
+        }
+    }
+
+    RgbaIcon::from_rgba(rgba, width as u32, height as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `BITMAPINFOHEADER` for a bottom-up, uncompressed bitmap.
+    fn info_header(width: i32, height: i32, bpp: u16) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&bpp.to_le_bytes());
+        header.extend_from_slice(&[0; 24]);
+        header
+    }
+
+    #[test]
+    fn decodes_bmp() {
+        // 2x2, 24 bpp, rows padded to 8 bytes and stored bottom-up
+        let pixels = [
+            0, 0, 255, 0, 255, 0, 0, 0, // bottom row: red, green
+            255, 0, 0, 255, 255, 255, 0, 0, // top row: blue, white
+        ];
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&((54 + pixels.len()) as u32).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&54u32.to_le_bytes());
+        bmp.extend_from_slice(&info_header(2, 2, 24));
+        bmp.extend_from_slice(&pixels);
+
+        let icon = decode(&bmp).unwrap();
+        assert_eq!((icon.width, icon.height), (2, 2));
+        assert_eq!(
+            icon.rgba,
+            [
+                0, 0, 255, 255, 255, 255, 255, 255, // blue, white
+                255, 0, 0, 255, 0, 255, 0, 255, // red, green
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_ico_with_mask() {
+        // 2x1, 32 bpp without alpha, the second pixel is transparent in the mask
+        let mut image = info_header(2, 2, 32);
+        image.extend_from_slice(&[10, 20, 30, 0, 40, 50, 60, 0]);
+        image.extend_from_slice(&[0b0100_0000, 0, 0, 0]);
+
+        let mut ico = vec![0, 0, 1, 0, 1, 0];
+        ico.extend_from_slice(&[2, 1, 0, 0, 1, 0, 32, 0]);
+        ico.extend_from_slice(&(image.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&22u32.to_le_bytes());
+        ico.extend_from_slice(&image);
+
+        let icon = decode(&ico).unwrap();
+        assert_eq!((icon.width, icon.height), (2, 1));
+        assert_eq!(icon.rgba, [30, 20, 10, 255, 60, 50, 40, 0]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_data() {
+        assert!(matches!(
+            decode(b"GIF89a"),
+            Err(BadIcon::UnsupportedFormat(_))
+        ));
+        assert!(matches!(decode(b"BM\0\0"), Err(BadIcon::DecodeFailed(_))));
+        assert!(matches!(
+            decode(&[0, 0, 1, 0, 1, 0]),
+            Err(BadIcon::DecodeFailed(_))
+        ));
+    }
+}
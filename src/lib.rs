@@ -15,6 +15,7 @@
 //! # Platform-specific notes:
 //!
 //! - On Windows and Linux, an event loop must be running on the thread, on Windows, a win32 event loop and on Linux, a gtk event loop. It doesn't need to be the main thread but you have to create the tray icon on the same thread as the event loop.
+//!   On Linux, a warning is logged through the [`log`](https://docs.rs/log) crate if the gtk event loop isn't iterated shortly after creating the first tray icon.
 //! - On macOS, an event loop must be running on the main thread so you also need to create the tray icon on the main thread. You must make sure that the event loop is already running and not just created before creating a TrayIcon to prevent issues with fullscreen apps. In Winit for example the earliest you can create icons is on [`StartCause::Init`](https://docs.rs/winit/latest/winit/event/enum.StartCause.html#variant.Init).
 //!
 //! # Dependencies (Linux Only)
@@ -121,13 +122,19 @@
 //! [tao]: https://docs.rs/tao
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    collections::HashMap,
     path::{Path, PathBuf},
-    rc::Rc,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use counter::Counter;
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use once_cell::sync::{Lazy, OnceCell};
 
 mod counter;
@@ -137,7 +144,7 @@ mod platform_impl;
 mod tray_icon_id;
 
 pub use self::error::*;
-pub use self::icon::{BadIcon, Icon};
+pub use self::icon::{BadIcon, Icon, MAX_ICON_DIMENSION};
 pub use self::tray_icon_id::TrayIconId;
 
 /// Re-export of [muda](::muda) crate and used for tray context menu.
@@ -146,6 +153,15 @@ pub mod menu {
 }
 pub use muda::dpi;
 
+/// Windows-specific helpers.
+#[cfg(target_os = "windows")]
+pub mod windows {
+    pub use crate::platform_impl::{
+        guid_in_use, position_window_near, shell_capabilities, taskbar_autohidden,
+        taskbar_position, Capabilities, NotificationIcon, TaskbarEdge,
+    };
+}
+
 static COUNTER: Counter = Counter::new();
 
 /// Attributes to use when creating a tray icon.
@@ -162,8 +178,20 @@ pub struct TrayIconAttributes {
     /// ## Platform-specific:
     ///
     /// - **Linux**: once a menu is set, it cannot be removed.
+    ///
+    /// The menu isn't cloned with the rest of the attributes, see the [`Clone`] implementation.
     pub menu: Option<Box<dyn menu::ContextMenu>>,
 
+    /// Function to build the tray menu right before it is shown, used when no
+    /// [`TrayIconAttributes::menu`] is set. **Windows only**.
+    ///
+    /// It isn't cloned with the rest of the attributes either.
+    pub menu_fn: Option<Box<dyn Fn() -> Box<dyn menu::ContextMenu>>>,
+
+    /// Whether to reuse the menu built by [`TrayIconAttributes::menu_fn`] on the next opens
+    /// instead of building it every time, default is `false`. **Windows only**.
+    pub cache_menu_fn: bool,
+
     /// Tray icon
     ///
     /// ## Platform-specific:
@@ -172,6 +200,10 @@ pub struct TrayIconAttributes {
     ///     Setting an empty [`Menu`](crate::menu::Menu) is enough.
     pub icon: Option<Icon>,
 
+    /// Icon to show instead of [`TrayIconAttributes::icon`] while the tray icon is marked as unread,
+    /// see [`TrayIcon::set_unread`].
+    pub unread_icon: Option<Icon>,
+
     /// Tray icon temp dir path. **Linux only**.
     pub temp_dir_path: Option<PathBuf>,
 
@@ -181,6 +213,46 @@ pub struct TrayIconAttributes {
     /// Whether to show the tray menu on left click or not, default is `true`. **macOS & Windows only**.
     pub menu_on_left_click: bool,
 
+    /// Whether to bring the tray icon's hidden window to the foreground when the menu
+    /// is opened by a left click, default is `true`. **Windows only**.
+    ///
+    /// See [`TrayIconBuilder::with_left_click_menu_foreground`] for the implications.
+    pub left_click_menu_foreground: bool,
+
+    /// Which kinds of [`TrayIconEvent`]s to emit for this tray icon, default is [`EventMask::default`],
+    /// all of them except [`EventMask::SCROLL`].
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub event_mask: EventMask,
+
+    /// Whether to emit [`TrayIconEvent::Raw`] for tray messages that aren't otherwise handled,
+    /// default is `false`. **Windows only**.
+    pub raw_events: bool,
+
+    /// Whether clicking the tray icon while its menu is open closes the menu instead of
+    /// opening it again, default is `false`. **Windows only**.
+    pub menu_toggle_on_click: bool,
+
+    /// Whether to emit [`TrayIconEvent::ContextRequested`] when the tray icon is right clicked
+    /// but no menu is shown, default is `false`.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub context_requested_events: bool,
+
+    /// How the tray menu is presented, default is [`MenuTheme::default`]. **Windows only**.
+    pub menu_theme: MenuTheme,
+
+    /// Where the tray menu opens relative to the cursor, default is [`MenuAlignment::BottomLeft`].
+    /// **Windows only**.
+    pub menu_alignment: MenuAlignment,
+
+    /// GUID identifying the tray icon to the shell, see [`TrayIconBuilder::with_guid`]. **Windows only**.
+    pub guid: Option<u128>,
+
     /// Tray icon title.
     ///
     /// ## Platform-specific
@@ -199,20 +271,64 @@ impl Default for TrayIconAttributes {
         Self {
             tooltip: None,
             menu: None,
+            menu_fn: None,
+            cache_menu_fn: false,
             icon: None,
+            unread_icon: None,
             temp_dir_path: None,
             icon_is_template: false,
             menu_on_left_click: true,
+            left_click_menu_foreground: true,
+            event_mask: EventMask::default(),
+            raw_events: false,
+            menu_toggle_on_click: false,
+            context_requested_events: false,
+            menu_theme: MenuTheme::default(),
+            menu_alignment: MenuAlignment::default(),
+            guid: None,
             title: None,
         }
     }
 }
 
+/// Clones every attribute except [`TrayIconAttributes::menu`] and [`TrayIconAttributes::menu_fn`],
+/// which are `None` in the clone since they are boxed and can't be cloned.
+///
+/// This is useful to create several similar tray icons from a template,
+/// set the menu on each clone afterwards.
+impl Clone for TrayIconAttributes {
+    fn clone(&self) -> Self {
+        Self {
+            tooltip: self.tooltip.clone(),
+            menu: None,
+            menu_fn: None,
+            cache_menu_fn: self.cache_menu_fn,
+            icon: self.icon.clone(),
+            unread_icon: self.unread_icon.clone(),
+            temp_dir_path: self.temp_dir_path.clone(),
+            icon_is_template: self.icon_is_template,
+            menu_on_left_click: self.menu_on_left_click,
+            left_click_menu_foreground: self.left_click_menu_foreground,
+            event_mask: self.event_mask,
+            raw_events: self.raw_events,
+            menu_toggle_on_click: self.menu_toggle_on_click,
+            context_requested_events: self.context_requested_events,
+            menu_theme: self.menu_theme,
+            menu_alignment: self.menu_alignment,
+            guid: self.guid,
+            title: self.title.clone(),
+        }
+    }
+}
+
 /// [`TrayIcon`] builder struct and associated methods.
 #[derive(Default)]
 pub struct TrayIconBuilder {
     id: TrayIconId,
     attrs: TrayIconAttributes,
+    /// State to apply once the tray icon is built, see [`TrayIconBuilder::restore`].
+    #[cfg(feature = "serde")]
+    restored: Option<TrayIconState>,
 }
 
 impl TrayIconBuilder {
@@ -223,10 +339,39 @@ impl TrayIconBuilder {
         Self {
             id: TrayIconId(COUNTER.next().to_string()),
             attrs: TrayIconAttributes::default(),
+            #[cfg(feature = "serde")]
+            restored: None,
+        }
+    }
+
+    /// Creates a new [`TrayIconBuilder`] from existing [`TrayIconAttributes`],
+    /// e.g. ones built from your app's configuration.
+    ///
+    /// Icons are validated when they are created, so this only checks the tooltip.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** Returns [`Error::TooltipTooLong`] if the tooltip is longer than
+    ///   127 UTF-16 code units, instead of truncating it like [`TrayIconBuilder::with_tooltip`].
+    pub fn from_attributes(attrs: TrayIconAttributes) -> Result<Self> {
+        #[cfg(target_os = "windows")]
+        if let Some(tooltip) = &attrs.tooltip {
+            platform_impl::check_tooltip_len(tooltip)?;
         }
+
+        Ok(Self {
+            id: TrayIconId(COUNTER.next().to_string()),
+            attrs,
+            #[cfg(feature = "serde")]
+            restored: None,
+        })
     }
 
     /// Sets the unique id to build the tray icon with.
+    ///
+    /// Events carry this id so they can be routed to the right tray icon, see [`TrayIconEvent::id`],
+    /// so it must be unique within the process. Generated ids are decimal numbers, prefer ids
+    /// that aren't numbers to avoid clashing with the ids of other tray icons.
     pub fn with_id<I: Into<TrayIconId>>(mut self, id: I) -> Self {
         self.id = id.into();
         self
@@ -242,6 +387,24 @@ impl TrayIconBuilder {
         self
     }
 
+    /// Set a function to build the tray menu right before it is shown, instead of
+    /// building it up front with [`TrayIconBuilder::with_menu`]. **Windows only**.
+    ///
+    /// This is useful for heavy menus or menus that depend on the current state of your app.
+    /// The menu is built again on every open unless [`TrayIconBuilder::with_cache_menu_fn`]
+    /// is enabled, and the function is ignored while a menu is set.
+    pub fn with_menu_fn(mut self, f: Box<dyn Fn() -> Box<dyn menu::ContextMenu>>) -> Self {
+        self.attrs.menu_fn = Some(f);
+        self
+    }
+
+    /// Whether to reuse the menu built by the function set with [`TrayIconBuilder::with_menu_fn`]
+    /// on the next opens instead of building it every time, default is `false`. **Windows only**.
+    pub fn with_cache_menu_fn(mut self, enable: bool) -> Self {
+        self.attrs.cache_menu_fn = enable;
+        self
+    }
+
     /// Set an icon for this tray icon.
     ///
     /// ## Platform-specific:
@@ -253,6 +416,13 @@ impl TrayIconBuilder {
         self
     }
 
+    /// Set an icon to show instead of the tray icon while it is marked as unread
+    /// with [`TrayIcon::set_unread`], e.g. the same icon with an alert dot.
+    pub fn with_unread_icon(mut self, icon: Icon) -> Self {
+        self.attrs.unread_icon = Some(icon);
+        self
+    }
+
     /// Set a tooltip for this tray icon.
     ///
     /// ## Platform-specific:
@@ -272,7 +442,7 @@ impl TrayIconBuilder {
     ///   updated information.  In general, it shouldn't be shown unless a
     ///   user requests it as it can take up a significant amount of space
     ///   on the user's panel.  This may not be shown in all visualizations.
-    /// - **Windows:** Unsupported.
+    /// - **Windows:** Unsupported, the title is only stored so it can be read with [`TrayIcon::title`].
     pub fn with_title<S: AsRef<str>>(mut self, title: S) -> Self {
         self.attrs.title.replace(title.as_ref().to_string());
         self
@@ -299,6 +469,105 @@ impl TrayIconBuilder {
         self
     }
 
+    /// Whether to bring the tray icon's hidden window to the foreground when the menu
+    /// is opened by a left click, default is `true`. **Windows only**.
+    ///
+    /// Windows only dismisses a popup menu on clicks outside of it when its owner window
+    /// is in the foreground, so disabling this keeps your app's window focused when the menu
+    /// opens but the menu may stay open until an item is selected or `Esc` is pressed.
+    /// Menus opened by a right click always bring the window to the foreground.
+    pub fn with_left_click_menu_foreground(mut self, enable: bool) -> Self {
+        self.attrs.left_click_menu_foreground = enable;
+        self
+    }
+
+    /// Set which kinds of [`TrayIconEvent`]s to emit for this tray icon, default is [`EventMask::default`],
+    /// all of them except [`EventMask::SCROLL`].
+    ///
+    /// Events not included in the mask are dropped before being sent, this doesn't affect
+    /// showing the tray menu.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub fn with_event_mask(mut self, mask: EventMask) -> Self {
+        self.attrs.event_mask = mask;
+        self
+    }
+
+    /// Whether to emit [`TrayIconEvent::Raw`] for tray messages that aren't otherwise handled,
+    /// default is `false`. **Windows only**.
+    ///
+    /// This is useful for debugging or handling shell notifications this crate doesn't model yet.
+    pub fn with_raw_events(mut self, enable: bool) -> Self {
+        self.attrs.raw_events = enable;
+        self
+    }
+
+    /// Whether clicking the tray icon while its menu is open closes the menu instead of
+    /// opening it again, default is `false`. **Windows only**.
+    ///
+    /// Without this, the click that dismisses the menu reaches the tray icon right after
+    /// and reopens the menu. The click is still emitted as a [`TrayIconEvent::Click`].
+    pub fn with_menu_toggle_on_click(mut self, enable: bool) -> Self {
+        self.attrs.menu_toggle_on_click = enable;
+        self
+    }
+
+    /// Whether to emit [`TrayIconEvent::ContextRequested`] when the tray icon is right clicked
+    /// but no menu is shown because none is set or it is disabled, default is `false`.
+    ///
+    /// This lets apps without a menu show their own UI for the click. The right click is still
+    /// emitted as a [`TrayIconEvent::Click`] before it.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub fn with_context_requested_events(mut self, enable: bool) -> Self {
+        self.attrs.context_requested_events = enable;
+        self
+    }
+
+    /// Set how the tray menu is presented, see [`MenuTheme`]. **Windows only**.
+    pub fn with_menu_theme(mut self, theme: MenuTheme) -> Self {
+        self.attrs.menu_theme = theme;
+        self
+    }
+
+    /// Set where the tray menu opens relative to the cursor, default is [`MenuAlignment::BottomLeft`].
+    /// **Windows only**.
+    ///
+    /// This is useful when the taskbar is docked to the top, left or right of the screen.
+    pub fn with_menu_alignment(mut self, alignment: MenuAlignment) -> Self {
+        self.attrs.menu_alignment = alignment;
+        self
+    }
+
+    /// Identify the tray icon to the shell with `guid` instead of its window. **Windows only**.
+    ///
+    /// This makes the shell remember the user's choice to show or hide the icon in the
+    /// taskbar overflow across restarts of the app. The GUID must be unique to this tray icon,
+    /// and Windows ties it to the path of the executable, so adding the icon fails if the
+    /// executable is moved while the GUID is still registered.
+    pub fn with_guid(mut self, guid: u128) -> Self {
+        self.attrs.guid = Some(guid);
+        self
+    }
+
+    /// Restores a [`TrayIconState`] saved with [`TrayIcon::state`], e.g. on the previous launch.
+    ///
+    /// The tooltip and title replace the ones set on this builder, while the visibility and
+    /// unread state are applied once the tray icon is built. The icons and menu aren't part
+    /// of the state and still have to be set on the builder, the unread state is only restored
+    /// if an unread icon is set with [`TrayIconBuilder::with_unread_icon`].
+    #[cfg(feature = "serde")]
+    pub fn restore(mut self, state: TrayIconState) -> Self {
+        self.attrs.tooltip = state.tooltip.clone();
+        self.attrs.title = state.title.clone();
+        self.restored = Some(state);
+        self
+    }
+
     /// Access the unique id that will be assigned to the tray icon
     /// this builder will create.
     pub fn id(&self) -> &TrayIconId {
@@ -307,17 +576,146 @@ impl TrayIconBuilder {
 
     /// Builds and adds a new [`TrayIcon`] to the system tray.
     pub fn build(self) -> Result<TrayIcon> {
-        TrayIcon::with_id(self.id, self.attrs)
+        let tray = TrayIcon::with_id(self.id, self.attrs)?;
+
+        #[cfg(feature = "serde")]
+        if let Some(state) = self.restored {
+            if !state.visible {
+                tray.set_visible(false)?;
+            }
+            tray.set_unread(state.unread)?;
+        }
+
+        Ok(tray)
+    }
+
+    /// Builds and adds a new [`TrayIcon`] to the system tray, then blocks until
+    /// [`TrayIcon::rect`] reports a non-empty rect or `timeout` elapses.
+    ///
+    /// This catches the system accepting the icon without showing it, e.g. to verify
+    /// the tray icon works on first run. Returns [`Error::NotShown`] and removes the icon
+    /// if it isn't shown in time.
+    ///
+    /// Events of the calling thread are processed while waiting, so an icon that is added
+    /// once the taskbar is created can still be confirmed. This also dispatches messages and
+    /// run loop sources of the app's own windows on that thread.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported, returns [`Error::NotSupported`] without building the tray icon.
+    pub fn build_and_confirm(self, timeout: Duration) -> Result<TrayIcon> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+        if cfg!(target_os = "linux") {
+            return Err(Error::NotSupported);
+        }
+
+        let tray = self.build()?;
+
+        let start = Instant::now();
+        loop {
+            let shown = tray
+                .rect()
+                .is_some_and(|rect| rect.size.width > 0 && rect.size.height > 0);
+            if shown {
+                return Ok(tray);
+            }
+            let Some(remaining) = timeout.checked_sub(start.elapsed()) else {
+                return Err(Error::NotShown);
+            };
+            #[cfg(any(target_os = "windows", target_os = "macos"))]
+            platform_impl::wait_for_messages(remaining.min(POLL_INTERVAL));
+            #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+            let _ = remaining;
+        }
     }
 }
 
 /// Tray icon struct and associated methods.
 ///
 /// This type is reference-counted and the icon is removed when the last instance is dropped.
+///
+/// ## Ordering
+///
+/// None of the supported platforms let apps choose where their tray icons are placed,
+/// so there is no API to set the order of tray icons.
+///
+/// - **Windows:** The shell places new icons next to the most recently added ones and lets
+///   the user rearrange or hide them. Positions are remembered per icon, which are identified
+///   by the window and the internal icon id, so creating your icons in the same order on every
+///   launch is the closest thing to a deterministic ordering.
+/// - **macOS:** Status items are placed to the left of the ones created before them and can
+///   be rearranged by the user with `⌘`-drag.
+/// - **Linux:** The order depends on the desktop environment.
 #[derive(Clone)]
 pub struct TrayIcon {
     id: TrayIconId,
     tray: Rc<RefCell<platform_impl::TrayIcon>>,
+    unread: Rc<RefCell<UnreadState>>,
+    /// Incremented on every icon change, so pending `TrayIcon::set_icon_async` calls know
+    /// they were superseded.
+    icon_generation: Rc<Cell<u64>>,
+    logical: Rc<RefCell<LogicalState>>,
+}
+
+thread_local! {
+    /// The tray icons created on this thread, see [`TrayIcon::by_id`].
+    static TRAY_ICONS: RefCell<HashMap<TrayIconId, WeakTrayIcon>> = RefCell::new(HashMap::new());
+}
+
+/// A [`TrayIcon`] that doesn't keep it alive, kept in [`TRAY_ICONS`].
+struct WeakTrayIcon {
+    id: TrayIconId,
+    tray: Weak<RefCell<platform_impl::TrayIcon>>,
+    unread: Weak<RefCell<UnreadState>>,
+    icon_generation: Weak<Cell<u64>>,
+    logical: Weak<RefCell<LogicalState>>,
+}
+
+impl WeakTrayIcon {
+    fn upgrade(&self) -> Option<TrayIcon> {
+        Some(TrayIcon {
+            id: self.id.clone(),
+            tray: self.tray.upgrade()?,
+            unread: self.unread.upgrade()?,
+            icon_generation: self.icon_generation.upgrade()?,
+            logical: self.logical.upgrade()?,
+        })
+    }
+}
+
+/// State that can't be queried from every platform, tracked for [`TrayIcon::is_visible`].
+struct LogicalState {
+    visible: bool,
+}
+
+impl LogicalState {
+    fn new() -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self { visible: true }))
+    }
+}
+
+/// The icons and tooltips swapped by [`TrayIcon::set_unread`].
+struct UnreadState {
+    /// The icon shown while not unread, as set with [`TrayIcon::set_icon`].
+    icon: Option<Icon>,
+    unread_icon: Option<Icon>,
+    /// The tooltip shown while not unread, as set with [`TrayIcon::set_tooltip`].
+    tooltip: Option<String>,
+    unread_tooltip: Option<String>,
+    unread: bool,
+}
+
+impl UnreadState {
+    fn new(attrs: &TrayIconAttributes) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            icon: attrs.icon.clone(),
+            unread_icon: attrs.unread_icon.clone(),
+            tooltip: attrs.tooltip.clone(),
+            unread_tooltip: None,
+            unread: false,
+        }))
+    }
 }
 
 impl TrayIcon {
@@ -327,15 +725,17 @@ impl TrayIcon {
     ///
     /// - **Linux:** Sometimes the icon won't be visible unless a menu is set.
     ///   Setting an empty [`Menu`](crate::menu::Menu) is enough.
+    /// - **Windows:** The hidden tray window is created as per-monitor v2 DPI aware on Windows 10 1607
+    ///   and later, so [`TrayIcon::rect`] and event positions are in physical pixels even if your app is
+    ///   DPI unaware. Apps that intentionally run DPI unaware have to convert them to their
+    ///   own virtualized coordinates, e.g. with `PhysicalToLogicalPointForPerMonitorDPI`.
+    /// - **Windows:** If explorer hasn't created the taskbar yet, e.g. when the app is started on login,
+    ///   the icon is added once the taskbar is created instead of returning an error.
     pub fn new(attrs: TrayIconAttributes) -> Result<Self> {
         let id = TrayIconId(COUNTER.next().to_string());
-        Ok(Self {
-            tray: Rc::new(RefCell::new(platform_impl::TrayIcon::new(
-                id.clone(),
-                attrs,
-            )?)),
-            id,
-        })
+        let unread = UnreadState::new(&attrs);
+        let tray = platform_impl::TrayIcon::new(id.clone(), attrs)?;
+        Ok(Self::from_platform(id, tray, unread))
     }
 
     /// Builds and adds a new tray icon to the system tray with the specified Id.
@@ -343,13 +743,75 @@ impl TrayIcon {
     /// See [`TrayIcon::new`] for more info.
     pub fn with_id<I: Into<TrayIconId>>(id: I, attrs: TrayIconAttributes) -> Result<Self> {
         let id = id.into();
-        Ok(Self {
-            tray: Rc::new(RefCell::new(platform_impl::TrayIcon::new(
-                id.clone(),
-                attrs,
-            )?)),
+        let unread = UnreadState::new(&attrs);
+        let tray = platform_impl::TrayIcon::new(id.clone(), attrs)?;
+        Ok(Self::from_platform(id, tray, unread))
+    }
+
+    /// Adds a new tray icon to the system tray using an existing window to receive
+    /// the tray icon messages instead of creating a hidden window for it. **Windows only**.
+    ///
+    /// The window is subclassed to handle the tray icon messages and `hwnd` must be a valid `HWND`.
+    ///
+    /// ## Ownership
+    ///
+    /// - The window is still owned by the caller and must outlive the returned [`TrayIcon`].
+    /// - Dropping the [`TrayIcon`] only removes the icon from the system tray and the
+    ///   subclass from the window, it doesn't destroy the window.
+    /// - Only one tray icon can be attached to a window at a time.
+    /// - The window mustn't use window messages and timer ids in the `6000..6100` range as they are used
+    ///   by the tray icon, and should be a top-level window so the icon is restored when explorer restarts.
+    #[cfg(target_os = "windows")]
+    pub fn attach_to<I: Into<TrayIconId>>(
+        hwnd: isize,
+        id: I,
+        attrs: TrayIconAttributes,
+    ) -> Result<Self> {
+        let id = id.into();
+        let unread = UnreadState::new(&attrs);
+        let tray = platform_impl::TrayIcon::attach_to(hwnd, id.clone(), attrs)?;
+        Ok(Self::from_platform(id, tray, unread))
+    }
+
+    /// Wraps the platform tray icon and registers it for [`TrayIcon::by_id`].
+    fn from_platform(
+        id: TrayIconId,
+        tray: platform_impl::TrayIcon,
+        unread: Rc<RefCell<UnreadState>>,
+    ) -> Self {
+        let tray = Self {
             id,
-        })
+            tray: Rc::new(RefCell::new(tray)),
+            unread,
+            icon_generation: Default::default(),
+            logical: LogicalState::new(),
+        };
+        TRAY_ICONS.with_borrow_mut(|icons| {
+            // forget the tray icons that were dropped since
+            icons.retain(|_, icon| icon.tray.strong_count() > 0);
+            icons.insert(tray.id.clone(), tray.downgrade());
+        });
+        tray
+    }
+
+    fn downgrade(&self) -> WeakTrayIcon {
+        WeakTrayIcon {
+            id: self.id.clone(),
+            tray: Rc::downgrade(&self.tray),
+            unread: Rc::downgrade(&self.unread),
+            icon_generation: Rc::downgrade(&self.icon_generation),
+            logical: Rc::downgrade(&self.logical),
+        }
+    }
+
+    /// Returns the tray icon with `id`, e.g. to act on the tray icon a [`TrayIconEvent`]
+    /// was emitted for, see [`TrayIconEvent::id`].
+    ///
+    /// Since [`TrayIcon`] can't be sent to other threads, only the tray icons created on the
+    /// calling thread are found, and `None` is returned once all clones of the tray icon are dropped.
+    /// If several tray icons have the same id, the last one created is returned.
+    pub fn by_id(id: &TrayIconId) -> Option<TrayIcon> {
+        TRAY_ICONS.with_borrow(|icons| icons.get(id)?.upgrade())
     }
 
     /// Returns the id associated with this tray icon.
@@ -357,11 +819,141 @@ impl TrayIcon {
         &self.id
     }
 
+    /// Returns the id of the tray icon whose menu was opened last.
+    ///
+    /// Menu items are clicked while their menu is open, so this is the tray icon a
+    /// [`MenuEvent`](crate::menu::MenuEvent) for the menu of a tray icon belongs to,
+    /// which is useful when several tray icons share a menu or its items.
+    /// Use [`TrayIcon::by_id`] to get the tray icon itself.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported, always returns `None`.
+    pub fn menu_owner_id() -> Option<TrayIconId> {
+        MENU_OWNER.lock().unwrap().clone()
+    }
+
     /// Set new tray icon. If `None` is provided, it will remove the icon.
+    ///
+    /// While the tray icon is marked as unread with [`TrayIcon::set_unread`], the new icon is
+    /// shown once it is no longer unread.
     pub fn set_icon(&self, icon: Option<Icon>) -> Result<()> {
+        self.next_icon_generation();
+        self.apply_icon(icon)
+    }
+
+    /// Set new tray icon once `icon` resolves, e.g. after fetching it over the network.
+    ///
+    /// The returned future borrows the tray icon, so it has to be awaited on the thread the tray
+    /// icon was created on, usually with the local executor of your event loop, and the icon is
+    /// set on that thread without blocking it. If another icon is set before `icon` resolves,
+    /// with this or any other method, the resolved icon is discarded.
+    #[cfg(feature = "async")]
+    pub async fn set_icon_async<F>(&self, icon: F) -> Result<()>
+    where
+        F: std::future::Future<Output = Option<Icon>>,
+    {
+        let generation = self.next_icon_generation();
+        let icon = icon.await;
+        if self.icon_generation.get() != generation {
+            return Ok(());
+        }
+        self.apply_icon(icon)
+    }
+
+    fn next_icon_generation(&self) -> u64 {
+        let generation = self.icon_generation.get().wrapping_add(1);
+        self.icon_generation.set(generation);
+        generation
+    }
+
+    fn apply_icon(&self, icon: Option<Icon>) -> Result<()> {
+        let mut unread = self.unread.borrow_mut();
+        unread.icon = icon.clone();
+        if unread.unread && unread.unread_icon.is_some() {
+            return Ok(());
+        }
         self.tray.borrow_mut().set_icon(icon)
     }
 
+    /// Set new tray icon from the contents of a PNG, ICO or BMP file, e.g. fetched over the network.
+    ///
+    /// See [`Icon::from_bytes`] for the supported formats.
+    #[cfg(feature = "icon-decoding")]
+    pub fn set_icon_from_bytes(&self, bytes: &[u8]) -> Result<()> {
+        self.set_icon(Some(Icon::from_bytes(bytes)?))
+    }
+
+    /// Set new tray icon from 32bpp RGBA data, e.g. rendered by your app for every update.
+    ///
+    /// See [`Icon::from_rgba`] for the requirements on `rgba`.
+    pub fn set_icon_from_rgba(&self, rgba: &[u8], width: u32, height: u32) -> Result<()> {
+        self.set_icon(Some(Icon::from_rgba(rgba.to_vec(), width, height)?))
+    }
+
+    /// Marks the tray icon as unread, showing the icon set with
+    /// [`TrayIconBuilder::with_unread_icon`] and the tooltip set with
+    /// [`TrayIcon::set_unread_tooltip`], or as read, showing the normal ones again.
+    ///
+    /// Does nothing if neither an unread icon nor an unread tooltip was set.
+    pub fn set_unread(&self, unread: bool) -> Result<()> {
+        let mut state = self.unread.borrow_mut();
+        if state.unread == unread || (state.unread_icon.is_none() && state.unread_tooltip.is_none())
+        {
+            return Ok(());
+        }
+
+        if let Some(unread_icon) = &state.unread_icon {
+            let icon = if unread {
+                Some(unread_icon.clone())
+            } else {
+                state.icon.clone()
+            };
+            self.tray.borrow_mut().set_icon(icon)?;
+        }
+        if let Some(unread_tooltip) = &state.unread_tooltip {
+            let tooltip = if unread {
+                Some(unread_tooltip.clone())
+            } else {
+                state.tooltip.clone()
+            };
+            self.tray.borrow_mut().set_tooltip(tooltip)?;
+        }
+        state.unread = unread;
+        Ok(())
+    }
+
+    /// Set a tooltip to show instead of the tray icon's tooltip while it is marked as unread
+    /// with [`TrayIcon::set_unread`], e.g. "3 new messages". If `None` is provided, the
+    /// normal tooltip is shown while unread as well.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** The tooltip is truncated to 127 UTF-16 units like [`TrayIcon::set_tooltip`].
+    /// - **Linux:** Unsupported.
+    pub fn set_unread_tooltip<S: AsRef<str>>(&self, tooltip: Option<S>) -> Result<()> {
+        let mut state = self.unread.borrow_mut();
+        state.unread_tooltip = tooltip.map(|t| t.as_ref().to_string());
+        if !state.unread {
+            return Ok(());
+        }
+
+        let tooltip = state
+            .unread_tooltip
+            .clone()
+            .or_else(|| state.tooltip.clone());
+        self.tray.borrow_mut().set_tooltip(tooltip)?;
+        if state.unread_icon.is_none() && state.unread_tooltip.is_none() {
+            state.unread = false;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the tray icon is marked as unread, see [`TrayIcon::set_unread`].
+    pub fn is_unread(&self) -> bool {
+        self.unread.borrow().unread
+    }
+
     /// Set new tray menu.
     ///
     /// ## Platform-specific:
@@ -371,91 +963,566 @@ impl TrayIcon {
         self.tray.borrow_mut().set_menu(menu)
     }
 
+    /// Set an icon to show instead of the tray icon while the mouse is hovering over it,
+    /// useful to show an affordance like a close button. If `None` is provided, the tray icon
+    /// is shown on hover as well. **Windows only**.
+    pub fn set_hover_icon(&self, icon: Option<Icon>) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().set_hover_icon(icon);
+        #[cfg(not(target_os = "windows"))]
+        let _ = icon;
+    }
+
+    /// Updates the popup menu handle used for the current tray menu without
+    /// detaching and reattaching the menu like [`TrayIcon::set_menu`] does.
+    ///
+    /// This is cheaper than calling [`TrayIcon::set_menu`] again when only
+    /// the contents of the same menu have changed. **Windows only**.
+    pub fn refresh_menu_handle(&self) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().refresh_menu_handle();
+    }
+
     /// Sets the tooltip for this tray icon.
     ///
+    /// While the tray icon is marked as unread with an unread tooltip, the new tooltip is
+    /// shown once it is no longer unread.
+    ///
     /// ## Platform-specific:
     ///
+    /// - **Windows:** The tooltip is truncated to 127 UTF-16 code units, without splitting
+    ///   a surrogate pair, to leave room for the nul terminator.
     /// - **Linux:** Unsupported
     pub fn set_tooltip<S: AsRef<str>>(&self, tooltip: Option<S>) -> Result<()> {
+        let tooltip = tooltip.map(|t| t.as_ref().to_string());
+        let mut unread = self.unread.borrow_mut();
+        unread.tooltip = tooltip.clone();
+        if unread.unread && unread.unread_tooltip.is_some() {
+            return Ok(());
+        }
         self.tray.borrow_mut().set_tooltip(tooltip)
     }
 
-    /// Sets the tooltip for this tray icon.
+    /// Returns the tooltip currently shown, which is the unread tooltip while the tray icon
+    /// is marked as unread with one.
     ///
     /// ## Platform-specific:
     ///
-    /// - **Linux:** The title will not be shown unless there is an icon
-    ///   as well.  The title is useful for numerical and other frequently
-    ///   updated information.  In general, it shouldn't be shown unless a
-    ///   user requests it as it can take up a significant amount of space
-    ///   on the user's panel.  This may not be shown in all visualizations.
-    /// - **Windows:** Unsupported
-    pub fn set_title<S: AsRef<str>>(&self, title: Option<S>) {
-        self.tray.borrow_mut().set_title(title)
+    /// - **Linux:** Unsupported, always returns `None`.
+    pub fn tooltip(&self) -> Option<String> {
+        self.tray.borrow().tooltip()
     }
 
-    /// Show or hide this tray icon
-    pub fn set_visible(&self, visible: bool) -> Result<()> {
-        self.tray.borrow_mut().set_visible(visible)
+    /// Rotates the tooltip through `tooltips`, showing the next one every `interval`.
+    /// **Windows only**.
+    ///
+    /// Each entry is truncated to the 127 UTF-16 units Windows can show. Calling
+    /// [`TrayIcon::set_tooltip`] while cycling changes the tooltip restored by
+    /// [`TrayIcon::stop_tooltip_cycle`]. An empty `tooltips` stops the cycle.
+    pub fn set_tooltip_cycle(&self, tooltips: Vec<String>, interval: Duration) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().set_tooltip_cycle(tooltips, interval);
+        #[cfg(not(target_os = "windows"))]
+        let _ = (tooltips, interval);
     }
 
-    /// Sets the tray icon temp dir path. **Linux only**.
+    /// Stops rotating the tooltip started with [`TrayIcon::set_tooltip_cycle`]
+    /// and restores the tooltip set with [`TrayIcon::set_tooltip`]. **Windows only**.
+    pub fn stop_tooltip_cycle(&self) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().stop_tooltip_cycle();
+    }
+
+    /// Animates the tray icon through `frames`, showing the next one every `interval`,
+    /// e.g. to show progress while a background task runs. **Windows only**.
     ///
-    /// On Linux, we need to write the icon to the disk and usually it will
-    /// be `$XDG_RUNTIME_DIR/tray-icon` or `$TEMP/tray-icon`.
-    pub fn set_temp_dir_path<P: AsRef<Path>>(&self, path: Option<P>) {
-        #[cfg(target_os = "linux")]
-        self.tray.borrow_mut().set_temp_dir_path(path);
-        #[cfg(not(target_os = "linux"))]
-        let _ = path;
+    /// Calling [`TrayIcon::set_icon`] while animating changes the icon restored by
+    /// [`TrayIcon::stop_animation`]. The hover icon set with [`TrayIcon::set_hover_icon`] is
+    /// still shown while the cursor is over the tray icon. An empty `frames` stops the animation.
+    pub fn set_animation(&self, frames: Vec<Icon>, interval: Duration) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().set_animation(frames, interval);
+        #[cfg(not(target_os = "windows"))]
+        let _ = (frames, interval);
     }
 
-    /// Set the current icon as a [template](https://developer.apple.com/documentation/appkit/nsimage/1520017-template?language=objc). **macOS only**.
-    pub fn set_icon_as_template(&self, is_template: bool) {
-        #[cfg(target_os = "macos")]
-        self.tray.borrow_mut().set_icon_as_template(is_template);
-        #[cfg(not(target_os = "macos"))]
-        let _ = is_template;
+    /// Stops the animation started with [`TrayIcon::set_animation`]
+    /// and restores the icon set with [`TrayIcon::set_icon`]. **Windows only**.
+    pub fn stop_animation(&self) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().stop_animation();
     }
 
-    pub fn set_icon_with_as_template(&self, icon: Option<Icon>, is_template: bool) -> Result<()> {
-        #[cfg(target_os = "macos")]
-        return self
-            .tray
-            .borrow_mut()
-            .set_icon_with_as_template(icon, is_template);
-        #[cfg(not(target_os = "macos"))]
-        {
-            let _ = icon;
-            let _ = is_template;
-            Ok(())
+    /// Animates the tray icon like [`TrayIcon::set_animation`], until the returned handle is
+    /// dropped or [`AnimationHandle::stop`] is called. **Windows only**.
+    ///
+    /// This scopes the animation to the lifetime of the handle, e.g. to a background task.
+    /// The handle doesn't keep the tray icon alive, see [`AnimationHandle`].
+    #[cfg(target_os = "windows")]
+    pub fn animate(&self, frames: Vec<Icon>, interval: Duration) -> AnimationHandle {
+        AnimationHandle {
+            inner: self.tray.borrow_mut().animate(frames, interval),
         }
     }
 
-    /// Disable or enable showing the tray menu on left click.
-    ///
-    /// ## Platform-specific:
+    /// Animates the tray icon through `frames` while `f` runs on a new thread, e.g. with a
+    /// spinner to show the app is busy, then restores the icon set with [`TrayIcon::set_icon`],
+    /// even if `f` panics. **Windows only**.
     ///
-    /// - **Linux:** Unsupported.
-    pub fn set_show_menu_on_left_click(&self, enable: bool) {
-        #[cfg(any(target_os = "macos", target_os = "windows"))]
-        self.tray.borrow_mut().set_show_menu_on_left_click(enable);
-        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-        let _ = enable;
+    /// Returns the handle of the thread running `f`. This is [`TrayIcon::animate`] with the
+    /// [`AnimationHandle`] moved to that thread, so the icon is restored once the event loop
+    /// handles the stop message, and joining the thread on the event loop thread doesn't deadlock.
+    #[cfg(target_os = "windows")]
+    pub fn with_busy<F, R>(
+        &self,
+        frames: Vec<Icon>,
+        interval: Duration,
+        f: F,
+    ) -> std::thread::JoinHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let animation = self.animate(frames, interval);
+        std::thread::spawn(move || {
+            // dropped once `f` returns or panics
+            let _animation = animation;
+            f()
+        })
     }
 
-    /// Get tray icon rect.
+    /// Sets `tooltip` while running `f`, then restores the previous tooltip,
+    /// even if `f` panics.
+    ///
+    /// This is useful for transient status like "Syncing…".
     ///
     /// ## Platform-specific:
     ///
-    /// - **Linux**: Unsupported.
-    pub fn rect(&self) -> Option<Rect> {
+    /// - **Linux:** Unsupported, `f` is still called.
+    pub fn with_temp_tooltip<S: AsRef<str>, R>(
+        &self,
+        tooltip: S,
+        f: impl FnOnce() -> R,
+    ) -> Result<R> {
+        struct RestoreTooltip<'a> {
+            tray: &'a TrayIcon,
+            previous: Option<String>,
+        }
+
+        impl Drop for RestoreTooltip<'_> {
+            fn drop(&mut self) {
+                let _ = self
+                    .tray
+                    .tray
+                    .borrow_mut()
+                    .set_tooltip(self.previous.take());
+            }
+        }
+
+        // create the guard first so a failed update is rolled back as well,
+        // and bypass `set_tooltip` so the shown tooltip isn't remembered as the normal one
+        let _guard = RestoreTooltip {
+            tray: self,
+            previous: self.tray.borrow().tooltip(),
+        };
+        self.tray.borrow_mut().set_tooltip(Some(tooltip))?;
+
+        Ok(f())
+    }
+
+    /// Sets the title for this tray icon.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** The title will not be shown unless there is an icon
+    ///   as well.  The title is useful for numerical and other frequently
+    ///   updated information.  In general, it shouldn't be shown unless a
+    ///   user requests it as it can take up a significant amount of space
+    ///   on the user's panel.  This may not be shown in all visualizations.
+    /// - **Windows:** Unsupported, the title is only stored so it can be read with [`TrayIcon::title`].
+    pub fn set_title<S: AsRef<str>>(&self, title: Option<S>) {
+        self.tray.borrow_mut().set_title(title)
+    }
+
+    /// Returns the title set with [`TrayIcon::set_title`] or [`TrayIconBuilder::with_title`].
+    pub fn title(&self) -> Option<String> {
+        self.tray.borrow().title()
+    }
+
+    /// Sets the label announced by screen readers for this tray icon.
+    /// If `None` is provided, the tooltip is announced instead.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** Unsupported, screen readers always announce the tooltip.
+    /// - **Linux:** Used as the description of the icon, which defaults to "tray icon".
+    pub fn set_accessible_label<S: AsRef<str>>(&self, label: Option<S>) {
+        #[cfg(not(target_os = "windows"))]
+        self.tray.borrow_mut().set_accessible_label(label);
+        #[cfg(target_os = "windows")]
+        let _ = label;
+    }
+
+    /// Show or hide this tray icon
+    pub fn set_visible(&self, visible: bool) -> Result<()> {
+        self.tray.borrow_mut().set_visible(visible)?;
+        self.logical.borrow_mut().visible = visible;
+        Ok(())
+    }
+
+    /// Returns whether this tray icon is visible, as last set with [`TrayIcon::set_visible`].
+    ///
+    /// This doesn't account for the icon being hidden by the system, e.g. in the overflow
+    /// area of the Windows taskbar.
+    pub fn is_visible(&self) -> bool {
+        self.logical.borrow().visible
+    }
+
+    /// Shows a balloon notification from the tray icon with `icon` next to `title`. **Windows only**.
+    ///
+    /// `title` is truncated to 63 and `body` to 255 UTF-16 code units. On Windows 10 and later
+    /// the notification is shown as a toast, and it isn't shown while the user has
+    /// notifications turned off or paused.
+    ///
+    /// Emits [`TrayIconEvent::Notification`] events as the notification is shown, clicked
+    /// and dismissed.
+    #[cfg(target_os = "windows")]
+    pub fn show_notification(
+        &self,
+        title: &str,
+        body: &str,
+        icon: windows::NotificationIcon,
+    ) -> Result<()> {
+        self.tray.borrow().show_notification(title, body, icon)
+    }
+
+    /// Shows the tray menu at the cursor position, as if the tray icon was right clicked, e.g. to
+    /// open it from a keyboard shortcut. Returns [`Error::NoMenu`] if there is no menu, neither
+    /// set with [`TrayIcon::set_menu`] nor built by [`TrayIconBuilder::with_menu_fn`].
+    /// **Windows only**.
+    ///
+    /// The menu is shown even if it was disabled with [`TrayIcon::set_menu_enabled`], since that
+    /// only stops clicks from opening it.
+    #[cfg(target_os = "windows")]
+    pub fn show_menu(&self) -> Result<()> {
+        self.tray.borrow_mut().show_menu(None)
+    }
+
+    /// Shows the tray menu at `position`, in physical screen coordinates, see [`TrayIcon::show_menu`].
+    /// **Windows only**.
+    #[cfg(target_os = "windows")]
+    pub fn show_menu_at(&self, position: dpi::PhysicalPosition<f64>) -> Result<()> {
+        self.tray.borrow_mut().show_menu(Some(position))
+    }
+
+    /// Shows the tray menu on the monitor `monitor`, an `HMONITOR`, see [`TrayIcon::show_menu`].
+    /// **Windows only**.
+    ///
+    /// The menu opens at `position`, in physical screen coordinates, or at the cursor position
+    /// if `None`, moved inside the work area of the monitor if needed, so it opens on that
+    /// monitor wherever the cursor and the tray icon are. Returns an error if `monitor`
+    /// isn't a valid monitor handle.
+    #[cfg(target_os = "windows")]
+    pub fn show_menu_on_monitor(
+        &self,
+        monitor: isize,
+        position: Option<dpi::PhysicalPosition<f64>>,
+    ) -> Result<()> {
+        self.tray
+            .borrow_mut()
+            .show_menu_on_monitor(monitor, position)
+    }
+
+    /// Removes the icon from the system tray, returning an error if the shell failed to remove it,
+    /// e.g. because it was already removed. **Windows only**.
+    ///
+    /// This lets apps detect a stuck icon before dropping the [`TrayIcon`], which removes the icon
+    /// as well but ignores errors. Use [`TrayIcon::set_visible`] to add the icon back.
+    #[cfg(target_os = "windows")]
+    pub fn remove(&self) -> Result<()> {
+        self.tray.borrow_mut().remove()?;
+        self.logical.borrow_mut().visible = false;
+        Ok(())
+    }
+
+    /// Returns a snapshot of the logical state of this tray icon, which can be saved and passed
+    /// to [`TrayIconBuilder::restore`] on the next launch.
+    #[cfg(feature = "serde")]
+    pub fn state(&self) -> TrayIconState {
+        let unread = self.unread.borrow();
+        TrayIconState {
+            visible: self.is_visible(),
+            tooltip: unread.tooltip.clone(),
+            title: self.title(),
+            unread: unread.unread,
+        }
+    }
+
+    /// Sets the tray icon temp dir path. **Linux only**.
+    ///
+    /// On Linux, we need to write the icon to the disk and usually it will
+    /// be `$XDG_RUNTIME_DIR/tray-icon` or `$TEMP/tray-icon`.
+    pub fn set_temp_dir_path<P: AsRef<Path>>(&self, path: Option<P>) {
+        #[cfg(target_os = "linux")]
+        self.tray.borrow_mut().set_temp_dir_path(path);
+        #[cfg(not(target_os = "linux"))]
+        let _ = path;
+    }
+
+    /// Set the current icon as a [template](https://developer.apple.com/documentation/appkit/nsimage/1520017-template?language=objc). **macOS only**.
+    pub fn set_icon_as_template(&self, is_template: bool) {
+        #[cfg(target_os = "macos")]
+        self.tray.borrow_mut().set_icon_as_template(is_template);
+        #[cfg(not(target_os = "macos"))]
+        let _ = is_template;
+    }
+
+    pub fn set_icon_with_as_template(&self, icon: Option<Icon>, is_template: bool) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        {
+            self.next_icon_generation();
+            let mut unread = self.unread.borrow_mut();
+            unread.icon = icon.clone();
+            if unread.unread && unread.unread_icon.is_some() {
+                self.tray.borrow_mut().set_icon_as_template(is_template);
+                return Ok(());
+            }
+            self.tray
+                .borrow_mut()
+                .set_icon_with_as_template(icon, is_template)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = icon;
+            let _ = is_template;
+            Ok(())
+        }
+    }
+
+    /// Disable or enable showing the tray menu on left click.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** Also shows the menu when the tray icon is activated with the keyboard.
+    /// - **Linux:** Unsupported.
+    pub fn set_show_menu_on_left_click(&self, enable: bool) {
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        self.tray.borrow_mut().set_show_menu_on_left_click(enable);
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        let _ = enable;
+    }
+
+    /// Set where the tray menu opens relative to the cursor, see
+    /// [`TrayIconBuilder::with_menu_alignment`]. **Windows only**.
+    ///
+    /// This is useful to follow the taskbar when it is moved to another edge of the screen.
+    pub fn set_menu_alignment(&self, alignment: MenuAlignment) {
+        #[cfg(target_os = "windows")]
+        self.tray.borrow_mut().set_menu_alignment(alignment);
+        #[cfg(not(target_os = "windows"))]
+        let _ = alignment;
+    }
+
+    /// Enable or disable showing the tray menu without detaching it, e.g. during a modal operation.
+    ///
+    /// Click events are still emitted while the menu is disabled.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub fn set_menu_enabled(&self, enabled: bool) {
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        self.tray.borrow_mut().set_menu_enabled(enabled);
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        let _ = enabled;
+    }
+
+    /// Returns the mouse button that most recently opened the tray menu, or `None` if the menu
+    /// hasn't been opened by a click yet.
+    ///
+    /// This is meant to be called while handling a [`MenuEvent`](crate::menu::MenuEvent)
+    /// from this tray icon's menu, to tell a menu opened with a left click apart from one
+    /// opened with a right click.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux**: Unsupported, always returns `None`.
+    pub fn menu_opened_with(&self) -> Option<MouseButton> {
+        self.tray.borrow().menu_opened_with()
+    }
+
+    /// Get tray icon rect.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux**: Unsupported.
+    /// - **Windows**: When the taskbar auto-hides, this returns the approximate position of the icon
+    ///   once the taskbar is revealed rather than its off-screen position, see
+    ///   [`windows::taskbar_autohidden`]. No event is emitted when the taskbar slides in or out.
+    ///   The rect is in physical pixels even if the calling thread isn't DPI aware, as are
+    ///   event positions for a window passed to [`TrayIcon::attach_to`].
+    pub fn rect(&self) -> Option<Rect> {
         self.tray.borrow().rect()
     }
+
+    /// Get tray icon rect in logical pixels, i.e. [`TrayIcon::rect`] divided by the scale factor
+    /// of the monitor the tray icon is on.
+    ///
+    /// Use this to position popups of UI toolkits that work in logical pixels, e.g. egui or winit
+    /// windows created with logical coordinates, instead of converting [`TrayIcon::rect`]
+    /// with the scale factor of another window.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux**: Unsupported.
+    pub fn rect_logical(&self) -> Option<LogicalRect> {
+        let rect = self.rect()?;
+        let scale_factor = self.tray.borrow().scale_factor()?;
+        Some(rect.to_logical(scale_factor))
+    }
+
+    /// Emits a [`TrayIconEvent::RectChanged`] with the current [`TrayIcon::rect`],
+    /// e.g. to position a window once the icon settled after startup without polling.
+    ///
+    /// Returns `false` and emits nothing if the rect couldn't be determined.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux**: Unsupported, always returns `false`.
+    pub fn request_rect_event(&self) -> bool {
+        let Some(rect) = self.rect() else {
+            return false;
+        };
+        TrayIconEvent::send(TrayIconEvent::RectChanged {
+            id: self.id.clone(),
+            rect,
+        });
+        true
+    }
+
+    /// Returns the handle of the window receiving the tray icon messages. **Windows only**.
+    ///
+    /// This is the hidden window created for the tray icon, or the window passed to
+    /// [`TrayIcon::attach_to`]. The hidden window can't be activated, so use
+    /// [`TrayIcon::owner_for_dialogs`] to parent message boxes and dialogs instead.
+    ///
+    /// The window can be subclassed further, but the messages the tray icon itself uses carry
+    /// pointers to Rust values and aren't part of the public API, control the tray icon through
+    /// its methods or a [`TrayIconHandle`] instead.
+    ///
+    /// Destroying the window, or removing the subclass of the tray icon, while the tray icon is
+    /// alive is undefined behavior.
+    #[cfg(target_os = "windows")]
+    pub fn hwnd(&self) -> isize {
+        self.tray.borrow().hwnd()
+    }
+
+    /// Returns the screen edge the taskbar showing this tray icon is docked to. **Windows only**.
+    ///
+    /// See [`windows::taskbar_position`] for the bounds of the taskbar as well.
+    #[cfg(target_os = "windows")]
+    pub fn taskbar_edge(&self) -> Option<windows::TaskbarEdge> {
+        windows::taskbar_position().map(|(edge, _)| edge)
+    }
+
+    /// Returns a window suitable as the owner of message boxes and dialogs
+    /// shown in response to a tray action. **Windows only**.
+    ///
+    /// The window is invisible and created the first time this is called, then it is moved
+    /// to the tray icon and brought to the foreground so the dialog doesn't open behind other
+    /// windows. Call this right before showing the dialog, and prefer one of your app's own
+    /// windows as the owner when there is one.
+    #[cfg(target_os = "windows")]
+    pub fn owner_for_dialogs(&self) -> Result<isize> {
+        self.tray.borrow_mut().owner_for_dialogs()
+    }
+
+    /// Returns a handle that can update this tray icon from other threads. **Windows only**.
+    #[cfg(target_os = "windows")]
+    pub fn handle(&self) -> TrayIconHandle {
+        TrayIconHandle {
+            id: self.id.clone(),
+            inner: self.tray.borrow().handle(),
+        }
+    }
+}
+
+/// Stops the animation started with [`TrayIcon::animate`] when dropped. **Windows only**.
+///
+/// The handle doesn't keep the tray icon alive and does nothing once the tray icon is dropped.
+/// It also does nothing once another animation is started or the animation is stopped with
+/// [`TrayIcon::set_animation`] or [`TrayIcon::stop_animation`], so it never stops an animation
+/// it didn't start.
+///
+/// Unlike [`TrayIcon`], the handle can be sent to other threads. The animation is stopped once
+/// the thread that created the tray icon handles the stop message, without blocking the thread
+/// the handle is dropped on.
+#[cfg(target_os = "windows")]
+#[must_use = "the animation is stopped when the handle is dropped"]
+pub struct AnimationHandle {
+    inner: platform_impl::AnimationHandle,
+}
+
+#[cfg(target_os = "windows")]
+impl AnimationHandle {
+    /// Stops the animation and restores the icon set with [`TrayIcon::set_icon`],
+    /// same as dropping the handle.
+    pub fn stop(self) {}
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for AnimationHandle {
+    fn drop(&mut self) {
+        self.inner.stop();
+    }
+}
+
+/// A handle to update a [`TrayIcon`] from any thread, returned by [`TrayIcon::handle`].
+/// **Windows only**.
+///
+/// Each call blocks until the thread that created the tray icon handles it, so don't call these
+/// from that thread while it isn't running its event loop. Once the tray icon is dropped,
+/// every method returns an error.
+///
+/// Changes made through the handle bypass the state kept by [`TrayIcon`], so they aren't
+/// reflected by [`TrayIcon::tooltip`], [`TrayIcon::is_visible`] or [`TrayIcon::set_unread`].
+#[cfg(target_os = "windows")]
+#[derive(Clone)]
+pub struct TrayIconHandle {
+    id: TrayIconId,
+    inner: platform_impl::TrayIconHandle,
+}
+
+#[cfg(target_os = "windows")]
+impl TrayIconHandle {
+    /// Returns the id of the tray icon this handle updates.
+    pub fn id(&self) -> &TrayIconId {
+        &self.id
+    }
+
+    /// Set new tray icon. If `None` is provided, it will remove the icon.
+    pub fn set_icon(&self, icon: Option<Icon>) -> Result<()> {
+        self.inner.set_icon(icon)
+    }
+
+    /// Set new tooltip for the tray icon, truncated like [`TrayIcon::set_tooltip`].
+    pub fn set_tooltip<S: AsRef<str>>(&self, tooltip: Option<S>) -> Result<()> {
+        self.inner
+            .set_tooltip(tooltip.map(|t| t.as_ref().to_string()))
+    }
+
+    /// Show or hide the tray icon.
+    pub fn set_visible(&self, visible: bool) -> Result<()> {
+        self.inner.set_visible(visible)
+    }
 }
 
 /// Describes a tray icon event.
 ///
+/// Prefer the [`TrayIconEvent::id`], [`TrayIconEvent::position`] and [`TrayIconEvent::rect`]
+/// accessors over matching on the fields shared by most variants, so your code keeps working
+/// as variants are added.
+///
 /// ## Platform-specific:
 ///
 /// - **Linux**: Unsupported. The event is not emmited even though the icon is shown
@@ -500,6 +1567,13 @@ pub enum TrayIconEvent {
         rect: Rect,
     },
     /// The mouse moved over the tray icon region.
+    ///
+    /// Emitted for every mouse move reported by the platform, which can be as often as every
+    /// frame, so consider debouncing expensive work like showing a preview popup.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** Moves that don't change the position are not emitted.
     Move {
         /// Id of the tray icon which triggered this event.
         id: TrayIconId,
@@ -509,6 +1583,11 @@ pub enum TrayIconEvent {
         rect: Rect,
     },
     /// The mouse left the tray icon region.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** The shell doesn't report the mouse leaving, so the cursor position is
+    ///   checked every 15ms while it is over the tray icon, and this is emitted up to 15ms late.
     Leave {
         /// Id of the tray icon which triggered this event.
         id: TrayIconId,
@@ -517,6 +1596,100 @@ pub enum TrayIconEvent {
         /// Position and size of the tray icon.
         rect: Rect,
     },
+    /// The tray icon was right clicked, or the menu key was pressed while it had the keyboard
+    /// focus, but no menu was shown for it.
+    ///
+    /// Only emitted when enabled with [`TrayIconBuilder::with_context_requested_events`].
+    ContextRequested {
+        /// Id of the tray icon which triggered this event.
+        id: TrayIconId,
+        /// Physical Position of this event.
+        position: dpi::PhysicalPosition<f64>,
+        /// Position and size of the tray icon.
+        rect: Rect,
+    },
+    /// The tray icon was activated, either by a left click or with the keyboard by pressing
+    /// Enter or Space while it is focused. **Windows only**.
+    ///
+    /// Respond to this instead of [`TrayIconEvent::Click`] to keep the tray icon usable without
+    /// a mouse. It's emitted after the [`TrayIconEvent::Click`] events of a left click.
+    Activate {
+        /// Id of the tray icon which triggered this event.
+        id: TrayIconId,
+        /// Physical Position of this event, the position of the tray icon when activated
+        /// with the keyboard.
+        position: dpi::PhysicalPosition<f64>,
+        /// Position and size of the tray icon.
+        rect: Rect,
+        /// Whether the tray icon was activated with the keyboard.
+        keyboard: bool,
+    },
+    /// A notification shown with the tray icon changed its state. **Windows only**.
+    ///
+    /// Respond to [`NotificationState::Clicked`] to e.g. open a window with the details
+    /// of the notification.
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+    Notification {
+        /// Id of the tray icon which triggered this event.
+        id: TrayIconId,
+        /// The new state of the notification.
+        state: NotificationState,
+    },
+    /// The mouse wheel or trackpad was scrolled over the tray icon.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** `delta` is in wheel notches. Only emitted when [`EventMask::SCROLL`] is
+    ///   included with [`TrayIconBuilder::with_event_mask`], see its docs for the cost.
+    /// - **Linux:** Unsupported.
+    Scroll {
+        /// Id of the tray icon which triggered this event.
+        id: TrayIconId,
+        /// Physical Position of this event.
+        position: dpi::PhysicalPosition<f64>,
+        /// Position and size of the tray icon.
+        rect: Rect,
+        /// Direction of the scroll.
+        direction: ScrollDirection,
+        /// Raw scroll amount along the axis of `direction`, positive when scrolling up or right.
+        delta: f64,
+    },
+    /// The position or size of the tray icon, emitted when requested with [`TrayIcon::request_rect_event`].
+    RectChanged {
+        /// Id of the tray icon which triggered this event.
+        id: TrayIconId,
+        /// Position and size of the tray icon.
+        rect: Rect,
+    },
+    /// A tray message that isn't otherwise handled was received. **Windows Only**
+    ///
+    /// Only emitted when enabled with [`TrayIconBuilder::with_raw_events`]. Tray icons opt into
+    /// `NOTIFYICON_VERSION_4` when the shell supports it, so this includes the `WM_CONTEXTMENU`
+    /// notification sent after a right click. The one sent when the menu key is pressed opens the
+    /// menu instead.
+    Raw {
+        /// Id of the tray icon which triggered this event.
+        id: TrayIconId,
+        /// The notification code, e.g. one of the `NIN_*` messages.
+        code: u32,
+    },
+}
+
+/// Describes the state of a notification, see [`TrayIconEvent::Notification`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotificationState {
+    /// The notification was shown.
+    Shown,
+    /// The notification was hidden, e.g. because the tray icon was removed.
+    Hidden,
+    /// The user clicked the notification.
+    Clicked,
+    /// The notification timed out or was dismissed by the user without clicking it.
+    ///
+    /// On Windows 10 and later, this is also emitted when the toast is moved to the
+    /// notification center.
+    TimedOut,
 }
 
 /// Describes the mouse button state.
@@ -533,6 +1706,35 @@ impl Default for MouseButtonState {
     }
 }
 
+/// Describes the direction of a [`TrayIconEvent::Scroll`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl ScrollDirection {
+    /// Returns the direction and the delta along its axis for the given scroll deltas,
+    /// which are positive when scrolling up or right, preferring the vertical axis.
+    #[cfg_attr(target_os = "linux", allow(unused))]
+    pub(crate) fn from_deltas(delta_x: f64, delta_y: f64) -> Option<(Self, f64)> {
+        if delta_y > 0. {
+            Some((Self::Up, delta_y))
+        } else if delta_y < 0. {
+            Some((Self::Down, delta_y))
+        } else if delta_x > 0. {
+            Some((Self::Right, delta_x))
+        } else if delta_x < 0. {
+            Some((Self::Left, delta_x))
+        } else {
+            None
+        }
+    }
+}
+
 /// Describes which mouse button triggered the event..
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -548,6 +1750,170 @@ impl Default for MouseButton {
     }
 }
 
+bitflags::bitflags! {
+    /// Describes which kinds of [`TrayIconEvent`]s should be emitted.
+    ///
+    /// See [`TrayIconBuilder::with_event_mask`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct EventMask: u32 {
+        /// [`TrayIconEvent::Click`] events with the left mouse button.
+        const LEFT = 1 << 0;
+        /// [`TrayIconEvent::Click`] events with the right mouse button.
+        const RIGHT = 1 << 1;
+        /// [`TrayIconEvent::Click`] events with the middle mouse button.
+        const MIDDLE = 1 << 2;
+        /// [`TrayIconEvent::DoubleClick`] events with any mouse button.
+        const DOUBLE_CLICK = 1 << 3;
+        /// [`TrayIconEvent::Enter`], [`TrayIconEvent::Move`] and [`TrayIconEvent::Leave`] events.
+        const HOVER = 1 << 4;
+        /// [`TrayIconEvent::Scroll`] events, not included by default.
+        ///
+        /// On Windows, the shell doesn't forward mouse wheel messages to tray icons, so a
+        /// system-wide low-level mouse hook (`WH_MOUSE_LL`) is installed while the cursor is
+        /// over the tray icon. Every mouse event on the system then waits for the thread of the
+        /// tray icon, and is delayed by up to the `LowLevelHooksTimeout` when that thread is
+        /// blocked, e.g. by [`TrayIconBuilder::build_and_confirm`] or a modal dialog.
+        const SCROLL = 1 << 5;
+        /// [`TrayIconEvent::Activate`] events.
+        const ACTIVATE = 1 << 6;
+        /// [`TrayIconEvent::Notification`] events.
+        const NOTIFICATION = 1 << 7;
+    }
+}
+
+impl Default for EventMask {
+    fn default() -> Self {
+        Self::all().difference(Self::SCROLL)
+    }
+}
+
+impl EventMask {
+    /// Whether `event` is included in this mask.
+    #[allow(unused)]
+    pub(crate) fn allows(&self, event: &TrayIconEvent) -> bool {
+        let kind = match event {
+            TrayIconEvent::Click {
+                button: MouseButton::Left,
+                ..
+            } => EventMask::LEFT,
+            TrayIconEvent::Click {
+                button: MouseButton::Right,
+                ..
+            } => EventMask::RIGHT,
+            TrayIconEvent::Click {
+                button: MouseButton::Middle,
+                ..
+            } => EventMask::MIDDLE,
+            TrayIconEvent::DoubleClick { .. } => EventMask::DOUBLE_CLICK,
+            TrayIconEvent::Enter { .. }
+            | TrayIconEvent::Move { .. }
+            | TrayIconEvent::Leave { .. } => EventMask::HOVER,
+            TrayIconEvent::Scroll { .. } => EventMask::SCROLL,
+            TrayIconEvent::Activate { .. } => EventMask::ACTIVATE,
+            TrayIconEvent::Notification { .. } => EventMask::NOTIFICATION,
+            // gated by `TrayIconAttributes::raw_events` and
+            // `TrayIconAttributes::context_requested_events` instead
+            TrayIconEvent::Raw { .. } | TrayIconEvent::ContextRequested { .. } => return true,
+            // only emitted on request
+            TrayIconEvent::RectChanged { .. } => return true,
+        };
+        self.contains(kind)
+    }
+}
+
+/// Describes how the tray menu is presented when it is shown, see [`TrayIconBuilder::with_menu_theme`].
+/// **Windows only**.
+///
+/// The default shows the menu as the system does. The menu is always scaled for the DPI of the monitor
+/// it opens on, regardless of the DPI awareness of the app, so there is no option for it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MenuTheme {
+    right_to_left: bool,
+    color_scheme: MenuColorScheme,
+}
+
+impl MenuTheme {
+    /// Set whether to lay out the menu right-to-left for right-to-left languages, which also mirrors
+    /// the horizontal [`MenuAlignment`], e.g. the default opens the menu to the left of the cursor.
+    pub fn with_right_to_left(mut self, right_to_left: bool) -> Self {
+        self.right_to_left = right_to_left;
+        self
+    }
+
+    /// Set the colors of the menu, default is [`MenuColorScheme::Default`].
+    pub fn with_color_scheme(mut self, color_scheme: MenuColorScheme) -> Self {
+        self.color_scheme = color_scheme;
+        self
+    }
+
+    /// Whether the menu is laid out right-to-left, see [`MenuTheme::with_right_to_left`].
+    pub fn right_to_left(&self) -> bool {
+        self.right_to_left
+    }
+
+    /// The colors of the menu, see [`MenuTheme::with_color_scheme`].
+    pub fn color_scheme(&self) -> MenuColorScheme {
+        self.color_scheme
+    }
+}
+
+/// Describes the colors of the tray menu, see [`MenuTheme::with_color_scheme`]. **Windows only**.
+///
+/// Menus are drawn by the system, which only supports dark menus on Windows 10 1903 and later,
+/// where the light menu is shown instead. Choosing any scheme other than
+/// [`MenuColorScheme::Default`] sets the preferred app mode of the whole process,
+/// so it affects the other menus of the app as well.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MenuColorScheme {
+    /// Leave the colors as they are, which is light unless the app opted into dark menus itself.
+    #[default]
+    Default,
+    /// Follow the app theme chosen in the system settings.
+    System,
+    /// Always show a dark menu.
+    Dark,
+    /// Always show a light menu.
+    Light,
+}
+
+/// The logical state of a tray icon, which apps can save to recreate the tray icon
+/// the same way on the next launch, see [`TrayIcon::state`] and [`TrayIconBuilder::restore`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct TrayIconState {
+    /// Whether the tray icon is visible, see [`TrayIcon::set_visible`].
+    pub visible: bool,
+    /// The tooltip shown while not unread, see [`TrayIcon::set_tooltip`].
+    pub tooltip: Option<String>,
+    /// The title, see [`TrayIcon::set_title`].
+    pub title: Option<String>,
+    /// Whether the tray icon is marked as unread, see [`TrayIcon::set_unread`].
+    pub unread: bool,
+}
+
+/// Describes which corner or edge of the tray menu is placed at the cursor when it opens,
+/// see [`TrayIconBuilder::with_menu_alignment`] and [`TrayIcon::set_menu_alignment`]. **Windows only**.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAlignment {
+    /// The top left corner, the menu opens below and to the right of the cursor.
+    TopLeft,
+    /// The center of the top edge, the menu opens below the cursor.
+    TopCenter,
+    /// The top right corner, the menu opens below and to the left of the cursor.
+    TopRight,
+    /// The bottom left corner, the menu opens above and to the right of the cursor.
+    #[default]
+    BottomLeft,
+    /// The center of the bottom edge, the menu opens above the cursor.
+    BottomCenter,
+    /// The bottom right corner, the menu opens above and to the left of the cursor.
+    BottomRight,
+}
+
 /// Describes a rectangle including position (x - y axis) and size.
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -565,12 +1931,55 @@ impl Default for Rect {
     }
 }
 
+impl Rect {
+    /// Converts this rect to logical pixels with `scale_factor`.
+    pub fn to_logical(&self, scale_factor: f64) -> LogicalRect {
+        LogicalRect {
+            size: self.size.to_logical(scale_factor),
+            position: self.position.to_logical(scale_factor),
+        }
+    }
+}
+
+/// Describes a rectangle in logical pixels, see [`TrayIcon::rect_logical`].
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalRect {
+    pub size: dpi::LogicalSize<f64>,
+    pub position: dpi::LogicalPosition<f64>,
+}
+
 /// A reciever that could be used to listen to tray events.
 pub type TrayIconEventReceiver = Receiver<TrayIconEvent>;
 type TrayIconEventHandler = Box<dyn Fn(TrayIconEvent) + Send + Sync + 'static>;
 
-static TRAY_CHANNEL: Lazy<(Sender<TrayIconEvent>, TrayIconEventReceiver)> = Lazy::new(unbounded);
+static TRAY_CHANNEL: Lazy<(Sender<TrayIconEvent>, TrayIconEventReceiver)> =
+    Lazy::new(|| match TRAY_CHANNEL_CAPACITY.get() {
+        Some(&capacity) => bounded(capacity),
+        None => unbounded(),
+    });
+/// The capacity of [`TRAY_CHANNEL`], see [`TrayIconEvent::set_channel_capacity`].
+static TRAY_CHANNEL_CAPACITY: OnceCell<usize> = OnceCell::new();
 static TRAY_EVENT_HANDLER: OnceCell<Option<TrayIconEventHandler>> = OnceCell::new();
+static DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+/// The id of the tray icon whose menu was opened last, see [`TrayIcon::menu_owner_id`].
+static MENU_OWNER: Lazy<Mutex<Option<TrayIconId>>> = Lazy::new(Default::default);
+
+/// Records that the menu of the tray icon with `id` is being opened.
+#[cfg_attr(target_os = "linux", allow(unused))]
+pub(crate) fn set_menu_owner(id: &TrayIconId) {
+    *MENU_OWNER.lock().unwrap() = Some(id.clone());
+}
+
+/// Diagnostic counters for the tray event channel, see [`TrayIconEvent::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrayIconEventStats {
+    /// Number of events waiting in the channel to be received.
+    pub queued: usize,
+    /// Number of events dropped because the channel was full, see
+    /// [`TrayIconEvent::set_channel_capacity`].
+    pub dropped: u64,
+}
 
 impl TrayIconEvent {
     /// Returns the id of the tray icon which triggered this event.
@@ -581,9 +1990,63 @@ impl TrayIconEvent {
             TrayIconEvent::Enter { id, .. } => id,
             TrayIconEvent::Move { id, .. } => id,
             TrayIconEvent::Leave { id, .. } => id,
+            TrayIconEvent::ContextRequested { id, .. } => id,
+            TrayIconEvent::Activate { id, .. } => id,
+            TrayIconEvent::Notification { id, .. } => id,
+            TrayIconEvent::Scroll { id, .. } => id,
+            TrayIconEvent::RectChanged { id, .. } => id,
+            TrayIconEvent::Raw { id, .. } => id,
         }
     }
 
+    /// Returns the physical position of the cursor when this event happened, or `None` for events
+    /// not triggered by the mouse, i.e. [`TrayIconEvent::Notification`], [`TrayIconEvent::RectChanged`]
+    /// and [`TrayIconEvent::Raw`].
+    pub fn position(&self) -> Option<dpi::PhysicalPosition<f64>> {
+        match self {
+            TrayIconEvent::Click { position, .. } => Some(*position),
+            TrayIconEvent::DoubleClick { position, .. } => Some(*position),
+            TrayIconEvent::Enter { position, .. } => Some(*position),
+            TrayIconEvent::Move { position, .. } => Some(*position),
+            TrayIconEvent::Leave { position, .. } => Some(*position),
+            TrayIconEvent::ContextRequested { position, .. } => Some(*position),
+            TrayIconEvent::Activate { position, .. } => Some(*position),
+            TrayIconEvent::Scroll { position, .. } => Some(*position),
+            TrayIconEvent::Notification { .. } => None,
+            TrayIconEvent::RectChanged { .. } => None,
+            TrayIconEvent::Raw { .. } => None,
+        }
+    }
+
+    /// Returns the position and size of the tray icon when this event happened, or `None` for
+    /// events without one, i.e. [`TrayIconEvent::Notification`] and [`TrayIconEvent::Raw`].
+    pub fn rect(&self) -> Option<Rect> {
+        match self {
+            TrayIconEvent::Click { rect, .. } => Some(*rect),
+            TrayIconEvent::DoubleClick { rect, .. } => Some(*rect),
+            TrayIconEvent::Enter { rect, .. } => Some(*rect),
+            TrayIconEvent::Move { rect, .. } => Some(*rect),
+            TrayIconEvent::Leave { rect, .. } => Some(*rect),
+            TrayIconEvent::ContextRequested { rect, .. } => Some(*rect),
+            TrayIconEvent::Activate { rect, .. } => Some(*rect),
+            TrayIconEvent::Scroll { rect, .. } => Some(*rect),
+            TrayIconEvent::RectChanged { rect, .. } => Some(*rect),
+            TrayIconEvent::Notification { .. } => None,
+            TrayIconEvent::Raw { .. } => None,
+        }
+    }
+
+    /// Returns whether this event happened on the primary monitor.
+    ///
+    /// Useful for multi-monitor apps that want to show popups on the screen
+    /// where the tray icon was interacted with. Always `false` for [`TrayIconEvent::Notification`]
+    /// and [`TrayIconEvent::Raw`].
+    #[cfg(target_os = "windows")]
+    pub fn is_on_primary_monitor(&self) -> bool {
+        let position = self.position().or(self.rect().map(|rect| rect.position));
+        position.is_some_and(platform_impl::is_on_primary_monitor)
+    }
+
     /// Gets a reference to the event channel's [`TrayIconEventReceiver`]
     /// which can be used to listen for tray events.
     ///
@@ -594,12 +2057,57 @@ impl TrayIconEvent {
         &TRAY_CHANNEL.1
     }
 
+    /// Blocks the current thread until a tray event is received or `timeout` elapses,
+    /// returning `None` if no event was received in time.
+    ///
+    /// This is a convenience over [`TrayIconEvent::receiver`] for apps that want to wait
+    /// briefly for events on each iteration of their loop instead of busy-polling with `try_recv`.
+    ///
+    /// ## Note
+    ///
+    /// This will not receive any events if [`TrayIconEvent::set_event_handler`] has been called with a `Some` value.
+    pub fn recv_timeout(timeout: Duration) -> Option<TrayIconEvent> {
+        TRAY_CHANNEL.1.recv_timeout(timeout).ok()
+    }
+
+    /// Returns diagnostic counters for the event channel associated with [`TrayIconEvent::receiver`].
+    ///
+    /// A growing [`TrayIconEventStats::queued`] count means events are produced faster than
+    /// they are received, which usually means the consumer is stalled.
+    ///
+    /// ## Note
+    ///
+    /// The channel is unbounded unless [`TrayIconEvent::set_channel_capacity`] was called, so
+    /// [`TrayIconEventStats::dropped`] stays 0 otherwise. Events passed to a handler set with
+    /// [`TrayIconEvent::set_event_handler`] are not counted.
+    pub fn stats() -> TrayIconEventStats {
+        TrayIconEventStats {
+            queued: TRAY_CHANNEL.1.len(),
+            dropped: DROPPED_EVENTS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Bounds the event channel associated with [`TrayIconEvent::receiver`] to `capacity` events,
+    /// so new events are dropped and counted in [`TrayIconEventStats::dropped`] instead of
+    /// piling up while the receiver is stalled. A `capacity` of 0 drops every event that isn't
+    /// received right away.
+    ///
+    /// The channel is unbounded by default. This has to be called before any event is sent
+    /// or received, and returns `false` without effect if the channel was already created
+    /// or a capacity was set before.
+    pub fn set_channel_capacity(capacity: usize) -> bool {
+        Lazy::get(&TRAY_CHANNEL).is_none() && TRAY_CHANNEL_CAPACITY.set(capacity).is_ok()
+    }
+
     /// Set a handler to be called for new events. Useful for implementing custom event sender.
     ///
     /// ## Note
     ///
     /// Calling this function with a `Some` value,
     /// will not send new events to the channel associated with [`TrayIconEvent::receiver`]
+    ///
+    /// The handler can only be set once, and only before the first event is emitted,
+    /// so set it before building any tray icon. Later calls have no effect.
     pub fn set_event_handler<F: Fn(TrayIconEvent) + Send + Sync + 'static>(f: Option<F>) {
         if let Some(f) = f {
             let _ = TRAY_EVENT_HANDLER.set(Some(Box::new(f)));
@@ -612,14 +2120,73 @@ impl TrayIconEvent {
     pub(crate) fn send(event: TrayIconEvent) {
         if let Some(handler) = TRAY_EVENT_HANDLER.get_or_init(|| None) {
             handler(event);
-        } else {
-            let _ = TRAY_CHANNEL.0.send(event);
+        } else if TRAY_CHANNEL.0.try_send(event).is_err() {
+            DROPPED_EVENTS.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn rect_to_logical() {
+        use super::{dpi, Rect};
+        let rect = Rect {
+            size: dpi::PhysicalSize::new(48, 30),
+            position: dpi::PhysicalPosition::new(300., 1050.),
+        };
+        let logical = rect.to_logical(1.5);
+        assert_eq!(logical.size, dpi::LogicalSize::new(32., 20.));
+        assert_eq!(logical.position, dpi::LogicalPosition::new(200., 700.));
+    }
+
+    #[test]
+    fn scroll_direction_from_deltas() {
+        use super::ScrollDirection;
+        assert_eq!(
+            ScrollDirection::from_deltas(1., -120.),
+            Some((ScrollDirection::Down, -120.))
+        );
+        assert_eq!(
+            ScrollDirection::from_deltas(-2.5, 0.),
+            Some((ScrollDirection::Left, -2.5))
+        );
+        assert_eq!(ScrollDirection::from_deltas(0., 0.), None);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn tray_icon_handle_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<super::TrayIconHandle>();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tray_icon_state_round_trips() {
+        use super::TrayIconState;
+        let state = TrayIconState {
+            visible: false,
+            tooltip: Some("Syncing".into()),
+            title: None,
+            unread: true,
+        };
+
+        let value = serde_json::to_value(&state).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "visible": false,
+                "tooltip": "Syncing",
+                "title": null,
+                "unread": true,
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<TrayIconState>(value).unwrap(),
+            state
+        );
+    }
 
     #[cfg(feature = "serde")]
     #[test]
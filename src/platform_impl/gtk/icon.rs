@@ -4,7 +4,7 @@
 
 use std::{fs::File, io::BufWriter, path::Path};
 
-use crate::icon::BadIcon;
+use crate::icon::{BadIcon, RgbaIcon};
 
 #[derive(Debug, Clone)]
 pub struct PlatformIcon {
@@ -15,13 +15,23 @@ pub struct PlatformIcon {
 
 impl PlatformIcon {
     pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+        let icon = RgbaIcon::from_rgba(rgba, width, height)?;
         Ok(Self {
-            rgba,
-            width: width as i32,
-            height: height as i32,
+            rgba: icon.rgba,
+            width: icon.width as i32,
+            height: icon.height as i32,
         })
     }
 
+    /// Whether `other` has the same pixels, which are kept in memory so comparing is cheap.
+    pub fn is_same(&self, other: &Self) -> bool {
+        self.width == other.width && self.height == other.height && self.rgba == other.rgba
+    }
+
+    pub fn to_rgba(&self) -> Result<RgbaIcon, BadIcon> {
+        RgbaIcon::from_rgba(self.rgba.clone(), self.width as u32, self.height as u32)
+    }
+
     pub fn write_to_png(&self, path: impl AsRef<Path>) -> crate::Result<()> {
         let png = File::create(path)?;
         let w = &mut BufWriter::new(png);
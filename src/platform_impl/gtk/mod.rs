@@ -3,7 +3,14 @@
 // SPDX-License-Identifier: MIT
 
 mod icon;
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Once,
+    },
+    time::Duration,
+};
 
 use crate::icon::Icon;
 pub(crate) use icon::PlatformIcon;
@@ -11,6 +18,10 @@ pub(crate) use icon::PlatformIcon;
 use crate::{TrayIconAttributes, TrayIconId, COUNTER};
 use libappindicator::{AppIndicator, AppIndicatorStatus};
 
+/// How long to wait for the gtk main context to be iterated before warning.
+const EVENT_LOOP_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+static EVENT_LOOP_CHECK: Once = Once::new();
+
 pub struct TrayIcon {
     id: u32,
     indicator: AppIndicator,
@@ -18,11 +29,16 @@ pub struct TrayIcon {
     path: PathBuf,
     counter: u32,
     menu: Option<Box<dyn muda::ContextMenu>>,
+    accessible_label: Option<String>,
+    title: Option<String>,
 }
 
 impl TrayIcon {
     pub fn new(_id: TrayIconId, attrs: TrayIconAttributes) -> crate::Result<Self> {
         let id = COUNTER.next();
+
+        EVENT_LOOP_CHECK.call_once(warn_if_event_loop_not_running);
+
         let mut indicator = AppIndicator::new("tray-icon tray app", "");
         indicator.set_status(AppIndicatorStatus::Active);
 
@@ -39,7 +55,7 @@ impl TrayIcon {
             indicator.set_menu(&mut menu.gtk_context_menu());
         }
 
-        if let Some(title) = attrs.title {
+        if let Some(title) = &attrs.title {
             indicator.set_label(title.as_str(), "");
         }
 
@@ -50,6 +66,8 @@ impl TrayIcon {
             temp_dir_path: attrs.temp_dir_path,
             counter: 0,
             menu: attrs.menu,
+            accessible_label: None,
+            title: attrs.title,
         })
     }
     pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<()> {
@@ -66,8 +84,10 @@ impl TrayIcon {
 
         self.indicator
             .set_icon_theme_path(&parent_path.to_string_lossy());
-        self.indicator
-            .set_icon_full(&icon_path.to_string_lossy(), "tray icon");
+        self.indicator.set_icon_full(
+            &icon_path.to_string_lossy(),
+            icon_description(self.accessible_label.as_deref()),
+        );
         self.path = icon_path;
 
         Ok(())
@@ -84,9 +104,26 @@ impl TrayIcon {
         Ok(())
     }
 
+    pub fn tooltip(&self) -> Option<String> {
+        None
+    }
+
     pub fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) {
         self.indicator
             .set_label(title.as_ref().map(|t| t.as_ref()).unwrap_or(""), "");
+        self.title = title.map(|t| t.as_ref().to_string());
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.title.clone()
+    }
+
+    pub fn set_accessible_label<S: AsRef<str>>(&mut self, label: Option<S>) {
+        self.accessible_label = label.map(|s| s.as_ref().to_string());
+        self.indicator.set_icon_full(
+            &self.path.to_string_lossy(),
+            icon_description(self.accessible_label.as_deref()),
+        );
     }
 
     pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
@@ -103,9 +140,22 @@ impl TrayIcon {
         self.temp_dir_path = path.map(|p| p.as_ref().to_path_buf());
     }
 
+    pub fn menu_opened_with(&self) -> Option<crate::MouseButton> {
+        None
+    }
+
     pub fn rect(&self) -> Option<crate::Rect> {
         None
     }
+
+    pub fn scale_factor(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// The accessible description of the icon, see [`TrayIcon::set_accessible_label`].
+fn icon_description(accessible_label: Option<&str>) -> &str {
+    accessible_label.unwrap_or("tray icon")
 }
 
 impl Drop for TrayIcon {
@@ -115,6 +165,27 @@ impl Drop for TrayIcon {
     }
 }
 
+/// Schedules an idle callback on the default main context and logs a warning
+/// if it wasn't dispatched in time, which means no gtk event loop is running and
+/// the tray icon and its menu won't work.
+fn warn_if_event_loop_not_running() {
+    let iterated = Arc::new(AtomicBool::new(false));
+
+    let iterated_c = iterated.clone();
+    gtk::glib::idle_add_once(move || iterated_c.store(true, Ordering::Relaxed));
+
+    std::thread::spawn(move || {
+        std::thread::sleep(EVENT_LOOP_CHECK_TIMEOUT);
+        if !iterated.load(Ordering::Relaxed) {
+            log::warn!(
+                "tray-icon: the gtk main context hasn't been iterated for {:?} after creating a tray icon, \
+                make sure a gtk event loop (e.g. `gtk::main()`) is running on the thread the tray icon was created on",
+                EVENT_LOOP_CHECK_TIMEOUT
+            );
+        }
+    });
+}
+
 /// Generates an icon path in one of the following dirs:
 /// 1. If `temp_icon_dir` is `Some` use that.
 /// 2. `$XDG_RUNTIME_DIR/tray-icon`
@@ -17,6 +17,15 @@ impl PlatformIcon {
         (self.0.width, self.0.height)
     }
 
+    /// Whether `other` has the same pixels, which are kept in memory so comparing is cheap.
+    pub fn is_same(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    pub fn to_rgba(&self) -> Result<RgbaIcon, BadIcon> {
+        Ok(self.0.clone())
+    }
+
     pub fn to_png(&self) -> crate::Result<Vec<u8>> {
         let mut png = Vec::new();
 
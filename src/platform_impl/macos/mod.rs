@@ -12,13 +12,16 @@ use objc2_app_kit::{
     NSCellImagePosition, NSEvent, NSImage, NSMenu, NSStatusBar, NSStatusItem, NSTrackingArea,
     NSTrackingAreaOptions, NSVariableStatusItemLength, NSView, NSWindow,
 };
-use objc2_foundation::{CGPoint, CGRect, CGSize, MainThreadMarker, NSData, NSSize, NSString};
+use objc2_foundation::{
+    CGPoint, CGRect, CGSize, MainThreadMarker, NSData, NSDate, NSDefaultRunLoopMode, NSRunLoop,
+    NSSize, NSString,
+};
 
 pub(crate) use self::icon::PlatformIcon;
 use crate::Error;
 use crate::{
-    icon::Icon, menu, MouseButton, MouseButtonState, Rect, TrayIconAttributes, TrayIconEvent,
-    TrayIconId,
+    icon::Icon, menu, EventMask, MouseButton, MouseButtonState, Rect, ScrollDirection,
+    TrayIconAttributes, TrayIconEvent, TrayIconId,
 };
 
 pub struct TrayIcon {
@@ -26,6 +29,8 @@ pub struct TrayIcon {
     tray_target: Option<Retained<TrayTarget>>,
     id: TrayIconId,
     attrs: TrayIconAttributes,
+    menu_enabled: bool,
+    accessible_label: Option<String>,
     mtm: MainThreadMarker,
 }
 
@@ -39,6 +44,8 @@ impl TrayIcon {
             tray_target: Some(tray_target),
             id,
             attrs,
+            menu_enabled: true,
+            accessible_label: None,
             mtm,
         };
 
@@ -85,6 +92,10 @@ impl TrayIcon {
                 ),
                 status_item: ns_status_item.retain(),
                 menu_on_left_click: Cell::new(attrs.menu_on_left_click),
+                menu_enabled: Cell::new(true),
+                menu_opened_with: Cell::new(None),
+                event_mask: attrs.event_mask,
+                context_requested_events: attrs.context_requested_events,
             });
             let tray_target: Retained<TrayTarget> =
                 msg_send_id![super(target), initWithFrame: frame];
@@ -148,9 +159,36 @@ impl TrayIcon {
             tray_target.update_dimensions();
         }
         self.attrs.tooltip = tooltip;
+        self.apply_accessible_label();
         Ok(())
     }
 
+    pub fn set_accessible_label<S: AsRef<str>>(&mut self, label: Option<S>) {
+        self.accessible_label = label.map(|s| s.as_ref().to_string());
+        self.apply_accessible_label();
+    }
+
+    /// Sets the accessibility label of the status item's button, falling back to the tooltip.
+    fn apply_accessible_label(&self) {
+        let Some(ns_status_item) = &self.ns_status_item else {
+            return;
+        };
+        let label = self
+            .accessible_label
+            .as_deref()
+            .or(self.attrs.tooltip.as_deref())
+            .map(NSString::from_str);
+        unsafe {
+            if let Some(button) = ns_status_item.button(self.mtm) {
+                let () = msg_send![&button, setAccessibilityLabel: label.as_deref()];
+            }
+        }
+    }
+
+    pub fn tooltip(&self) -> Option<String> {
+        self.attrs.tooltip.clone()
+    }
+
     fn set_tooltip_inner<S: AsRef<str>>(
         ns_status_item: &NSStatusItem,
         tooltip: Option<S>,
@@ -175,16 +213,20 @@ impl TrayIcon {
         self.attrs.title = title;
     }
 
+    pub fn title(&self) -> Option<String> {
+        self.attrs.title.clone()
+    }
+
     fn set_title_inner<S: AsRef<str>>(
         ns_status_item: &NSStatusItem,
         title: Option<S>,
         mtm: MainThreadMarker,
     ) {
-        if let Some(title) = title {
-            unsafe {
-                if let Some(button) = ns_status_item.button(mtm) {
-                    button.setTitle(&NSString::from_str(title.as_ref()));
-                }
+        // an empty title removes the label
+        let title = title.as_ref().map(|t| t.as_ref()).unwrap_or("");
+        unsafe {
+            if let Some(button) = ns_status_item.button(mtm) {
+                button.setTitle(&NSString::from_str(title));
             }
         }
     }
@@ -193,8 +235,10 @@ impl TrayIcon {
         if visible {
             if self.ns_status_item.is_none() {
                 let (ns_status_item, tray_target) = Self::create(&self.id, &self.attrs, self.mtm)?;
+                tray_target.ivars().menu_enabled.set(self.menu_enabled);
                 self.ns_status_item = Some(ns_status_item);
                 self.tray_target = Some(tray_target);
+                self.apply_accessible_label();
             }
         } else {
             self.remove();
@@ -243,6 +287,19 @@ impl TrayIcon {
         self.attrs.menu_on_left_click = enable;
     }
 
+    pub fn set_menu_enabled(&mut self, enabled: bool) {
+        if let Some(tray_target) = &self.tray_target {
+            tray_target.ivars().menu_enabled.set(enabled);
+        }
+        self.menu_enabled = enabled;
+    }
+
+    pub fn menu_opened_with(&self) -> Option<MouseButton> {
+        self.tray_target
+            .as_ref()
+            .and_then(|tray_target| tray_target.ivars().menu_opened_with.get())
+    }
+
     pub fn rect(&self) -> Option<Rect> {
         let ns_status_item = self.ns_status_item.as_deref()?;
         unsafe {
@@ -251,6 +308,14 @@ impl TrayIcon {
             window.map(|window| get_tray_rect(&window))
         }
     }
+
+    pub fn scale_factor(&self) -> Option<f64> {
+        let ns_status_item = self.ns_status_item.as_deref()?;
+        unsafe {
+            let button = ns_status_item.button(self.mtm).unwrap();
+            button.window().map(|window| window.backingScaleFactor())
+        }
+    }
 }
 
 impl Drop for TrayIcon {
@@ -279,7 +344,12 @@ fn set_icon_for_ns_status_item_button(
             // build our icon
             let nsdata = NSData::from_vec(png_icon);
 
-            let nsimage = NSImage::initWithData(NSImage::alloc(), &nsdata).unwrap();
+            let nsimage = NSImage::initWithData(NSImage::alloc(), &nsdata).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "failed to create an NSImage from the icon",
+                )
+            })?;
             let new_size = NSSize::new(icon_width, icon_height);
 
             button.setImage(Some(&nsimage));
@@ -301,6 +371,10 @@ struct TrayTargetIvars {
     menu: RefCell<Option<Retained<NSMenu>>>,
     status_item: Retained<NSStatusItem>,
     menu_on_left_click: Cell<bool>,
+    menu_enabled: Cell<bool>,
+    menu_opened_with: Cell<Option<MouseButton>>,
+    event_mask: EventMask,
+    context_requested_events: bool,
 }
 
 declare_class!(
@@ -329,7 +403,7 @@ declare_class!(
                     state: MouseButtonState::Down,
                 }),
             );
-            on_tray_click(self, MouseButton::Left);
+            on_tray_click(self, event, MouseButton::Left);
         }
 
         #[method(mouseUp:)]
@@ -361,7 +435,7 @@ declare_class!(
                     state: MouseButtonState::Down,
                 }),
             );
-            on_tray_click(self, MouseButton::Right);
+            on_tray_click(self, event, MouseButton::Right);
         }
 
         #[method(rightMouseUp:)]
@@ -423,6 +497,15 @@ declare_class!(
         fn on_mouse_moved(&self, event: &NSEvent) {
             send_mouse_event(self, event, MouseEventType::Move, None);
         }
+
+        #[method(scrollWheel:)]
+        fn on_scroll_wheel(&self, event: &NSEvent) {
+            // positive horizontal deltas scroll to the left
+            let (delta_x, delta_y) = unsafe { (-event.scrollingDeltaX(), event.scrollingDeltaY()) };
+            if let Some((direction, delta)) = ScrollDirection::from_deltas(delta_x, delta_y) {
+                send_mouse_event(self, event, MouseEventType::Scroll(direction, delta), None);
+            }
+        }
     }
 
     // Tracking mouse enter/exit/move events
@@ -471,22 +554,29 @@ impl TrayTarget {
     }
 }
 
-fn on_tray_click(this: &TrayTarget, button: MouseButton) {
+fn on_tray_click(this: &TrayTarget, event: &NSEvent, button: MouseButton) {
     let mtm = MainThreadMarker::from(this);
     unsafe {
         let ns_button = this.ivars().status_item.button(mtm).unwrap();
 
         let menu_on_left_click = this.ivars().menu_on_left_click.get();
         if button == MouseButton::Right || (menu_on_left_click && button == MouseButton::Left) {
-            let has_items = if let Some(menu) = &*this.ivars().menu.borrow() {
+            let has_items = if !this.ivars().menu_enabled.get() {
+                false
+            } else if let Some(menu) = &*this.ivars().menu.borrow() {
                 menu.numberOfItems() > 0
             } else {
                 false
             };
             if has_items {
+                this.ivars().menu_opened_with.set(Some(button));
+                crate::set_menu_owner(&TrayIconId(this.ivars().id.to_string()));
                 ns_button.performClick(None);
             } else {
                 ns_button.highlight(true);
+                if button == MouseButton::Right && this.ivars().context_requested_events {
+                    send_mouse_event(this, event, MouseEventType::ContextRequested, None);
+                }
             }
         } else {
             ns_button.highlight(true);
@@ -558,9 +648,23 @@ fn send_mouse_event(
                 position: cursor_position,
                 rect: icon_rect,
             },
+            MouseEventType::ContextRequested => TrayIconEvent::ContextRequested {
+                id: tray_id,
+                position: cursor_position,
+                rect: icon_rect,
+            },
+            MouseEventType::Scroll(direction, delta) => TrayIconEvent::Scroll {
+                id: tray_id,
+                position: cursor_position,
+                rect: icon_rect,
+                direction,
+                delta,
+            },
         };
 
-        TrayIconEvent::send(event);
+        if this.ivars().event_mask.allows(&event) {
+            TrayIconEvent::send(event);
+        }
     }
 }
 
@@ -570,6 +674,8 @@ enum MouseEventType {
     Enter,
     Leave,
     Move,
+    ContextRequested,
+    Scroll(ScrollDirection, f64),
 }
 
 #[derive(Debug)]
@@ -592,3 +698,15 @@ struct MouseClickEvent {
 fn flip_window_screen_coordinates(y: f64) -> f64 {
     CGDisplay::main().pixels_high() as f64 - y
 }
+
+/// Runs the run loop of the calling thread for up to `timeout`, so the status item keeps
+/// being updated while the caller blocks.
+pub(crate) fn wait_for_messages(timeout: std::time::Duration) {
+    unsafe {
+        let limit = NSDate::dateWithTimeIntervalSinceNow(timeout.as_secs_f64());
+        // returns right away when the run loop has no sources to wait on
+        if !NSRunLoop::currentRunLoop().runMode_beforeDate(NSDefaultRunLoopMode, &limit) {
+            std::thread::sleep(timeout);
+        }
+    }
+}
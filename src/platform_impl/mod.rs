@@ -13,3 +13,8 @@ mod platform;
 mod platform;
 
 pub(crate) use self::platform::*;
+#[cfg(target_os = "windows")]
+pub use self::platform::{
+    guid_in_use, position_window_near, shell_capabilities, taskbar_autohidden, taskbar_position,
+    Capabilities, NotificationIcon, TaskbarEdge,
+};
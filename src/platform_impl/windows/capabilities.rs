@@ -0,0 +1,100 @@
+// Copyright 2022-2022 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use windows_sys::{
+    s, w,
+    Win32::{
+        Foundation::{NTSTATUS, STATUS_SUCCESS},
+        System::{
+            LibraryLoader::{GetModuleHandleW, GetProcAddress},
+            SystemInformation::OSVERSIONINFOW,
+        },
+    },
+};
+
+/// Tray related features supported by the current Windows version, see [`shell_capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Whether the shell supports `NOTIFYICON_VERSION_4` notifications, Windows Vista and later.
+    pub version_4: bool,
+    /// Whether tray icons can be identified by a GUID, Windows 7 and later.
+    pub guid: bool,
+    /// Whether the shell can report the position of tray icons, used by [`TrayIcon::rect`](crate::TrayIcon::rect)
+    /// and [`TrayIconEvent::RectChanged`](crate::TrayIconEvent::RectChanged), Windows 7 and later.
+    pub icon_rect: bool,
+    /// Whether the tray window can be per-monitor DPI aware, see [`TrayIcon::new`](crate::TrayIcon::new),
+    /// Windows 10 1607 and later.
+    pub per_monitor_dpi: bool,
+    /// Whether the tray menu can be dark, see [`MenuColorScheme`](crate::MenuColorScheme),
+    /// Windows 10 1903 and later.
+    pub dark_menus: bool,
+}
+
+impl Capabilities {
+    /// The features supported by Windows `major.minor` with the given `build` number.
+    fn for_version(major: u32, minor: u32, build: u32) -> Self {
+        let version = (major, minor);
+        Self {
+            version_4: version >= (6, 0),
+            guid: version >= (6, 1),
+            icon_rect: version >= (6, 1),
+            per_monitor_dpi: major >= 10 && build >= 14393,
+            dark_menus: major >= 10 && build >= 18362,
+        }
+    }
+}
+
+/// Returns which tray related features are supported by the current Windows version.
+///
+/// The version is read with `RtlGetVersion`, since the version reported by `GetVersionExW`
+/// depends on the app's manifest.
+pub fn shell_capabilities() -> Capabilities {
+    type RtlGetVersion = unsafe extern "system" fn(*mut OSVERSIONINFOW) -> NTSTATUS;
+
+    unsafe {
+        let ntdll = GetModuleHandleW(w!("ntdll.dll"));
+        let rtl_get_version = match ntdll.is_null() {
+            true => None,
+            false => GetProcAddress(ntdll, s!("RtlGetVersion"))
+                .map(|f| std::mem::transmute::<_, RtlGetVersion>(f)),
+        };
+
+        let mut info = OSVERSIONINFOW {
+            dwOSVersionInfoSize: std::mem::size_of::<OSVERSIONINFOW>() as _,
+            ..std::mem::zeroed()
+        };
+        match rtl_get_version {
+            Some(rtl_get_version) if rtl_get_version(&mut info) == STATUS_SUCCESS => {
+                Capabilities::for_version(
+                    info.dwMajorVersion,
+                    info.dwMinorVersion,
+                    info.dwBuildNumber,
+                )
+            }
+            // not expected, ntdll is loaded into every process and exports it since Windows 2000
+            _ => Capabilities::for_version(0, 0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_follow_the_windows_version() {
+        let vista = Capabilities::for_version(6, 0, 6000);
+        assert!(vista.version_4 && !vista.guid && !vista.icon_rect && !vista.per_monitor_dpi);
+
+        let windows_7 = Capabilities::for_version(6, 1, 7601);
+        assert!(windows_7.guid && windows_7.icon_rect && !windows_7.per_monitor_dpi);
+
+        assert!(!Capabilities::for_version(10, 0, 10586).per_monitor_dpi);
+        assert!(Capabilities::for_version(10, 0, 14393).per_monitor_dpi);
+
+        assert!(!Capabilities::for_version(10, 0, 17763).dark_menus);
+        assert!(Capabilities::for_version(10, 0, 18362).dark_menus);
+    }
+}
@@ -8,8 +8,15 @@ use std::{fmt, io, mem, path::Path, sync::Arc};
 
 use windows_sys::{
     core::PCWSTR,
-    Win32::UI::WindowsAndMessaging::{
-        CreateIcon, DestroyIcon, LoadImageW, HICON, IMAGE_ICON, LR_DEFAULTSIZE, LR_LOADFROMFILE,
+    Win32::{
+        Graphics::Gdi::{
+            DeleteObject, GetDC, GetDIBits, GetDeviceCaps, GetObjectW, ReleaseDC, BITMAP,
+            BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, LOGPIXELSX,
+        },
+        UI::WindowsAndMessaging::{
+            CopyIcon, CreateIcon, DestroyIcon, GetIconInfo, LoadImageW, HICON, ICONINFO,
+            IMAGE_ICON, LR_DEFAULTSIZE, LR_LOADFROMFILE, USER_DEFAULT_SCREEN_DPI,
+        },
     },
 };
 
@@ -24,6 +31,8 @@ impl Pixel {
 }
 
 impl RgbaIcon {
+    /// `CreateIcon` expects 32bpp color bitmaps with straight alpha and premultiplies the pixels
+    /// itself when the icon is drawn.
     fn into_windows_icon(self) -> Result<WinIcon, BadIcon> {
         let rgba = self.rgba;
         let pixel_count = rgba.len() / PIXEL_SIZE;
@@ -76,6 +85,32 @@ impl WinIcon {
         rgba_icon.into_windows_icon()
     }
 
+    /// Whether `other` is a clone of this icon, sharing its `HICON`.
+    pub(crate) fn is_same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Reads back the 32bpp RGBA pixels of this icon from its color bitmap.
+    pub(crate) fn to_rgba(&self) -> Result<RgbaIcon, BadIcon> {
+        unsafe {
+            let mut info: ICONINFO = mem::zeroed();
+            if GetIconInfo(self.as_raw_handle(), &mut info) == 0 {
+                return Err(BadIcon::OsError(io::Error::last_os_error()));
+            }
+
+            let result = read_color_bitmap(&info);
+
+            if !info.hbmColor.is_null() {
+                DeleteObject(info.hbmColor);
+            }
+            if !info.hbmMask.is_null() {
+                DeleteObject(info.hbmMask);
+            }
+
+            result
+        }
+    }
+
     pub(crate) fn from_handle(handle: HICON) -> Self {
         Self {
             #[allow(clippy::arc_with_non_send_sync)]
@@ -83,6 +118,15 @@ impl WinIcon {
         }
     }
 
+    pub(crate) fn from_shared_handle(handle: HICON) -> Result<Self, BadIcon> {
+        let handle = unsafe { CopyIcon(handle) };
+        if !handle.is_null() {
+            Ok(WinIcon::from_handle(handle))
+        } else {
+            Err(BadIcon::OsError(io::Error::last_os_error()))
+        }
+    }
+
     pub(crate) fn from_path<P: AsRef<Path>>(
         path: P,
         size: Option<(u32, u32)>,
@@ -109,9 +153,32 @@ impl WinIcon {
         }
     }
 
+    pub(crate) fn from_path_for_dpi<P: AsRef<Path>>(path: P, dpi: u32) -> Result<Self, BadIcon> {
+        if dpi == 0 {
+            return Err(BadIcon::OsError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the DPI must be greater than zero",
+            )));
+        }
+
+        let path = path.as_ref();
+        let size = small_icon_size(dpi);
+        Self::from_path(path, Some((size, size))).map_err(|e| match e {
+            BadIcon::OsError(e) => BadIcon::OsError(io::Error::new(
+                e.kind(),
+                format!(
+                    "failed to load a {size}x{size} icon for {dpi} DPI from {}: {e}",
+                    path.display()
+                ),
+            )),
+            e => e,
+        })
+    }
+
     fn from_resource_inner_name(name: PCWSTR, size: Option<(u32, u32)>) -> Result<Self, BadIcon> {
         // width / height of 0 along with LR_DEFAULTSIZE tells windows to load the default icon size
         let (width, height) = size.unwrap_or((0, 0));
+        // without LR_SHARED we get our own copy of the icon, so it can be destroyed on drop
         let handle = unsafe {
             LoadImageW(
                 util::get_instance_handle(),
@@ -145,6 +212,90 @@ impl WinIcon {
     }
 }
 
+/// The DPI of the primary monitor, falling back to 96 if it can't be queried.
+pub(crate) fn system_dpi() -> u32 {
+    unsafe {
+        let hdc = GetDC(std::ptr::null_mut());
+        if hdc.is_null() {
+            return USER_DEFAULT_SCREEN_DPI;
+        }
+        let dpi = GetDeviceCaps(hdc, LOGPIXELSX as _);
+        ReleaseDC(std::ptr::null_mut(), hdc);
+        if dpi > 0 {
+            dpi as u32
+        } else {
+            USER_DEFAULT_SCREEN_DPI
+        }
+    }
+}
+
+/// The size of a small icon, as used in the notification area, at `dpi`.
+fn small_icon_size(dpi: u32) -> u32 {
+    // small icons are 16x16 at 96 DPI, round to the nearest pixel
+    (16 * dpi + USER_DEFAULT_SCREEN_DPI / 2) / USER_DEFAULT_SCREEN_DPI
+}
+
+unsafe fn read_color_bitmap(info: &ICONINFO) -> Result<RgbaIcon, BadIcon> {
+    // monochrome icons only have a mask bitmap
+    if info.hbmColor.is_null() {
+        return Err(BadIcon::OsError(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "monochrome icons have no color bitmap to read pixels from",
+        )));
+    }
+
+    let mut bitmap: BITMAP = mem::zeroed();
+    if GetObjectW(
+        info.hbmColor,
+        mem::size_of::<BITMAP>() as i32,
+        &mut bitmap as *mut _ as _,
+    ) == 0
+    {
+        return Err(BadIcon::OsError(io::Error::last_os_error()));
+    }
+
+    let width = bitmap.bmWidth;
+    let height = bitmap.bmHeight;
+
+    let mut bitmap_info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            // negative height requests a top-down DIB, matching our RGBA row order
+            biHeight: -height,
+            biPlanes: 1,
+            biBitCount: (PIXEL_SIZE * 8) as u16,
+            biCompression: BI_RGB,
+            ..mem::zeroed()
+        },
+        ..mem::zeroed()
+    };
+
+    let mut bgra = vec![0u8; width as usize * height as usize * PIXEL_SIZE];
+    let hdc = GetDC(std::ptr::null_mut());
+    let lines = GetDIBits(
+        hdc,
+        info.hbmColor,
+        0,
+        height as u32,
+        bgra.as_mut_ptr() as _,
+        &mut bitmap_info,
+        DIB_RGB_COLORS,
+    );
+    ReleaseDC(std::ptr::null_mut(), hdc);
+
+    if lines == 0 {
+        return Err(BadIcon::OsError(io::Error::last_os_error()));
+    }
+
+    // BGRA -> RGBA
+    for pixel in bgra.chunks_exact_mut(PIXEL_SIZE) {
+        pixel.swap(0, 2);
+    }
+
+    RgbaIcon::from_rgba(bgra, width as u32, height as u32)
+}
+
 impl Drop for RaiiIcon {
     fn drop(&mut self) {
         unsafe { DestroyIcon(self.handle) };
@@ -156,3 +307,50 @@ impl fmt::Debug for WinIcon {
         (*self.inner).fmt(formatter)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_icon_size_scales_with_dpi() {
+        assert_eq!(small_icon_size(96), 16);
+        assert_eq!(small_icon_size(120), 20);
+        assert_eq!(small_icon_size(144), 24);
+        assert_eq!(small_icon_size(168), 28);
+        assert_eq!(small_icon_size(192), 32);
+    }
+
+    #[test]
+    fn dropping_icons_destroys_their_handles() {
+        use windows_sys::Win32::System::Threading::{
+            GetCurrentProcess, GetGuiResources, GR_GDIOBJECTS, GR_USEROBJECTS,
+        };
+
+        let handle_counts = || unsafe {
+            let process = GetCurrentProcess();
+            (
+                GetGuiResources(process, GR_GDIOBJECTS),
+                GetGuiResources(process, GR_USEROBJECTS),
+            )
+        };
+
+        let (gdi, user) = handle_counts();
+        // more icons than the default limit of 10000 handles per process
+        for _ in 0..20_000 {
+            let icon = WinIcon::from_rgba(vec![255; 16 * 16 * PIXEL_SIZE], 16, 16).unwrap();
+            drop(icon);
+        }
+        let (gdi_after, user_after) = handle_counts();
+
+        // leave some room for handles created by other tests running in parallel
+        assert!(
+            gdi_after < gdi + 100,
+            "{gdi} GDI handles before, {gdi_after} after"
+        );
+        assert!(
+            user_after < user + 100,
+            "{user} USER handles before, {user_after} after"
+        );
+    }
+}
@@ -2,40 +2,62 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+mod capabilities;
 mod icon;
+mod notification;
+mod position;
 mod util;
-use std::ptr;
+use std::{cell::Cell, ptr, rc::Rc, time::Duration};
 
 use once_cell::sync::Lazy;
 use windows_sys::{
-    s,
+    core::GUID,
+    s, w,
     Win32::{
-        Foundation::{FALSE, HWND, LPARAM, LRESULT, POINT, RECT, S_OK, TRUE, WPARAM},
+        Foundation::{GetLastError, FALSE, HWND, LPARAM, LRESULT, POINT, RECT, S_OK, TRUE, WPARAM},
         UI::{
+            Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON, VK_MBUTTON, VK_RBUTTON},
             Shell::{
-                Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP,
-                NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW, NOTIFYICONIDENTIFIER,
+                DefSubclassProc, GetWindowSubclass, RemoveWindowSubclass, SetWindowSubclass,
+                Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_GUID, NIF_ICON, NIF_MESSAGE,
+                NIF_SHOWTIP, NIF_STATE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIM_SETFOCUS,
+                NIM_SETVERSION, NINF_KEY, NIN_BALLOONHIDE, NIN_BALLOONSHOW, NIN_BALLOONTIMEOUT,
+                NIN_BALLOONUSERCLICK, NIN_SELECT, NIS_HIDDEN, NOTIFYICONDATAW, NOTIFYICONDATAW_0,
+                NOTIFYICONIDENTIFIER, NOTIFYICON_VERSION_4,
             },
             WindowsAndMessaging::{
-                CreateWindowExW, DefWindowProcW, DestroyWindow, GetCursorPos, KillTimer,
-                RegisterClassW, RegisterWindowMessageA, SendMessageW, SetForegroundWindow,
-                SetTimer, TrackPopupMenu, CREATESTRUCTW, CW_USEDEFAULT, GWL_USERDATA, HICON, HMENU,
-                TPM_BOTTOMALIGN, TPM_LEFTALIGN, WM_CREATE, WM_DESTROY, WM_LBUTTONDBLCLK,
-                WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP,
-                WM_MOUSEMOVE, WM_NCCREATE, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP,
-                WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
-                WS_EX_TRANSPARENT, WS_OVERLAPPED,
+                CallNextHookEx, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
+                FindWindowW, GetCursorPos, IsMenu, IsWindow, KillTimer, MsgWaitForMultipleObjects,
+                PeekMessageW, PostMessageW, PostQuitMessage, RegisterClassW,
+                RegisterWindowMessageA, SendMessageW, SetForegroundWindow, SetTimer, SetWindowPos,
+                SetWindowsHookExW, TrackPopupMenu, TranslateMessage, UnhookWindowsHookEx,
+                CREATESTRUCTW, CW_USEDEFAULT, GWL_USERDATA, HC_ACTION, HHOOK, HICON, HMENU,
+                HWND_MESSAGE, MSG, MSLLHOOKSTRUCT, PM_REMOVE, QS_ALLINPUT, SWP_NOACTIVATE,
+                SWP_NOSIZE, SWP_NOZORDER, TPM_BOTTOMALIGN, TPM_CENTERALIGN, TPM_LAYOUTRTL,
+                TPM_LEFTALIGN, TPM_RIGHTALIGN, TPM_TOPALIGN, TRACK_POPUP_MENU_FLAGS,
+                USER_DEFAULT_SCREEN_DPI, WHEEL_DELTA, WH_MOUSE_LL, WM_CONTEXTMENU, WM_CREATE,
+                WM_DESTROY, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK,
+                WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+                WM_NCCREATE, WM_NULL, WM_QUIT, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP,
+                WM_TIMER, WNDCLASSW, WNDPROC, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+                WS_EX_TRANSPARENT, WS_OVERLAPPED, WS_POPUP,
             },
         },
     },
 };
 
 use crate::{
-    dpi::PhysicalPosition, icon::Icon, menu, MouseButton, MouseButtonState, Rect,
-    TrayIconAttributes, TrayIconEvent, TrayIconId, COUNTER,
+    dpi::PhysicalPosition, icon::Icon, menu, EventMask, MenuAlignment, MenuTheme, MouseButton,
+    MouseButtonState, NotificationState, Rect, ScrollDirection, TrayIconAttributes, TrayIconEvent,
+    TrayIconId, COUNTER,
 };
 
+pub use self::capabilities::{shell_capabilities, Capabilities};
+pub(crate) use self::icon::system_dpi;
 pub(crate) use self::icon::WinIcon as PlatformIcon;
+pub use self::notification::NotificationIcon;
+pub(crate) use self::position::is_on_primary_monitor;
+pub use self::position::{position_window_near, taskbar_autohidden, taskbar_position, TaskbarEdge};
 
 const WM_USER_TRAYICON: u32 = 6002;
 const WM_USER_UPDATE_TRAYMENU: u32 = 6003;
@@ -45,60 +67,249 @@ const WM_USER_HIDE_TRAYICON: u32 = 6006;
 const WM_USER_UPDATE_TRAYTOOLTIP: u32 = 6007;
 const WM_USER_LEAVE_TIMER_ID: u32 = 6008;
 const WM_USER_SHOW_MENU_ON_LEFT_CLICK: u32 = 6009;
+const WM_USER_UPDATE_HOVER_ICON: u32 = 6010;
+const WM_USER_TOOLTIP_CYCLE_TIMER_ID: u32 = 6011;
+const WM_USER_UPDATE_TOOLTIP_CYCLE: u32 = 6012;
+const WM_USER_SET_MENU_ENABLED: u32 = 6013;
+const WM_USER_SHOW_MENU: u32 = 6014;
+const WM_USER_SCROLL: u32 = 6015;
+const WM_USER_ANIMATION_TIMER_ID: u32 = 6016;
+const WM_USER_UPDATE_ANIMATION: u32 = 6017;
+const WM_USER_UPDATE_MENU_ALIGNMENT: u32 = 6018;
+const WM_USER_STOP_ANIMATION: u32 = 6019;
+/// Sent instead of [`NIN_SELECT`] when the tray icon is activated with the keyboard.
+const NIN_KEYSELECT: u32 = NIN_SELECT | NINF_KEY;
+/// Subclass id used when attaching to a window we don't own, see [`TrayIcon::attach_to`].
+const TRAY_SUBCLASS_ID: usize = 6001;
 /// When the taskbar is created, it registers a message with the "TaskbarCreated" string and then broadcasts this message to all top-level windows
 /// When the application receives this message, it should assume that any taskbar icons it added have been removed and add them again.
 static S_U_TASKBAR_RESTART: Lazy<u32> =
     Lazy::new(|| unsafe { RegisterWindowMessageA(s!("TaskbarCreated")) });
 
+/// The window class of the hidden tray windows, see [`register_window_class`].
+static TRAY_CLASS_NAME: Lazy<Result<Vec<u16>, i32>> = Lazy::new(|| unsafe {
+    register_window_class(
+        concat!("tray_icon_app_", env!("CARGO_PKG_VERSION")),
+        Some(tray_proc),
+    )
+});
+/// The window class of the windows created by [`TrayIcon::owner_for_dialogs`].
+static DIALOG_OWNER_CLASS_NAME: Lazy<Result<Vec<u16>, i32>> = Lazy::new(|| unsafe {
+    register_window_class(
+        concat!("tray_icon_dialog_owner_", env!("CARGO_PKG_VERSION")),
+        Some(DefWindowProcW),
+    )
+});
+
+thread_local! {
+    /// The low-level mouse hook installed while the cursor is over a tray icon,
+    /// and the window of that icon, see [`set_scroll_hook`].
+    static SCROLL_HOOK: Cell<Option<(HHOOK, HWND)>> = const { Cell::new(None) };
+}
+
 struct TrayUserData {
     internal_id: u32,
+    guid: Option<u128>,
+    /// Whether the shell sends `NOTIFYICON_VERSION_4` messages, see [`set_tray_version`].
+    version_4: bool,
     id: TrayIconId,
     hwnd: HWND,
     hpopupmenu: Option<HMENU>,
+    menu_fn: Option<Box<dyn Fn() -> Box<dyn menu::ContextMenu>>>,
+    cache_menu_fn: bool,
+    /// The menu last built by `menu_fn`, kept attached until the next open
+    /// since its commands are handled after it closes.
+    lazy_menu: Option<Box<dyn menu::ContextMenu>>,
     icon: Option<Icon>,
     tooltip: Option<String>,
     entered: bool,
     last_position: Option<PhysicalPosition<f64>>,
     menu_on_left_click: bool,
+    menu_enabled: bool,
+    hover_icon: Option<Icon>,
+    menu_opened_with: Rc<Cell<Option<MouseButton>>>,
+    event_mask: EventMask,
+    left_click_menu_foreground: bool,
+    raw_events: bool,
+    context_requested_events: bool,
+    menu_theme: MenuTheme,
+    menu_alignment: MenuAlignment,
+    menu_toggle_on_click: bool,
+    /// Whether the menu was just dismissed by pressing a mouse button over the tray icon,
+    /// so the button down message that follows shouldn't open it again.
+    menu_dismissed_by_icon_click: bool,
+    /// Whether the right button was just released over the tray icon, so the `WM_CONTEXTMENU`
+    /// that follows it came from the mouse and not from the keyboard.
+    right_button_released: bool,
+    /// Tooltips to rotate through, see [`TrayIcon::set_tooltip_cycle`].
+    tooltip_cycle: Vec<String>,
+    tooltip_cycle_index: usize,
+    /// Icons to animate through, see [`TrayIcon::set_animation`].
+    animation: Vec<Icon>,
+    animation_index: usize,
+    /// Incremented whenever the animation changes, so an [`AnimationHandle`] doesn't stop
+    /// an animation it didn't start.
+    animation_generation: usize,
+}
+
+impl TrayUserData {
+    fn new(
+        id: TrayIconId,
+        internal_id: u32,
+        hwnd: HWND,
+        attrs: &mut TrayIconAttributes,
+        menu_opened_with: Rc<Cell<Option<MouseButton>>>,
+    ) -> Self {
+        Self {
+            id,
+            internal_id,
+            guid: attrs.guid,
+            version_4: false,
+            hwnd,
+            hpopupmenu: attrs.menu.as_ref().map(|m| m.hpopupmenu() as _),
+            menu_fn: attrs.menu_fn.take(),
+            cache_menu_fn: attrs.cache_menu_fn,
+            lazy_menu: None,
+            icon: attrs.icon.clone(),
+            tooltip: attrs.tooltip.clone(),
+            entered: false,
+            last_position: None,
+            menu_on_left_click: attrs.menu_on_left_click,
+            menu_enabled: true,
+            hover_icon: None,
+            menu_opened_with,
+            event_mask: attrs.event_mask,
+            left_click_menu_foreground: attrs.left_click_menu_foreground,
+            raw_events: attrs.raw_events,
+            context_requested_events: attrs.context_requested_events,
+            menu_toggle_on_click: attrs.menu_toggle_on_click,
+            menu_theme: attrs.menu_theme,
+            menu_alignment: attrs.menu_alignment,
+            menu_dismissed_by_icon_click: false,
+            right_button_released: false,
+            tooltip_cycle: Vec::new(),
+            tooltip_cycle_index: 0,
+            animation: Vec::new(),
+            animation_index: 0,
+            animation_generation: 0,
+        }
+    }
+
+    /// Animates the tray icon through `frames` every `interval`, or restores the icon set with
+    /// [`TrayIcon::set_icon`] if `None` or empty, returning the new animation generation.
+    unsafe fn set_animation(&mut self, animation: Option<(Vec<Icon>, Duration)>) -> usize {
+        let hwnd = self.hwnd;
+        KillTimer(hwnd, WM_USER_ANIMATION_TIMER_ID as _);
+        self.animation_index = 0;
+        self.animation = match animation {
+            Some((frames, interval)) if !frames.is_empty() => {
+                let interval = interval.as_millis().clamp(1, u32::MAX as u128) as u32;
+                SetTimer(
+                    hwnd,
+                    WM_USER_ANIMATION_TIMER_ID as _,
+                    interval,
+                    Some(tray_timer_proc),
+                );
+                frames
+            }
+            // restore the icon set with `set_icon`
+            _ => Vec::new(),
+        };
+
+        // the hover icon stays until the cursor leaves
+        if !(self.entered && self.hover_icon.is_some()) {
+            modify_tray_icon(hwnd, self.internal_id, self.guid, self.shown_icon());
+        }
+
+        self.animation_generation = self.animation_generation.wrapping_add(1);
+        self.animation_generation
+    }
+
+    /// Returns the icon to show while the cursor isn't over the tray icon,
+    /// the current animation frame or the icon set with [`TrayIcon::set_icon`].
+    fn shown_icon(&self) -> Option<&Icon> {
+        self.animation
+            .get(self.animation_index)
+            .or(self.icon.as_ref())
+    }
+
+    /// Returns the menu to show, the one set with [`TrayIcon::set_menu`] or else the one built by
+    /// `menu_fn`.
+    ///
+    /// A menu that was destroyed while still set, e.g. because it was dropped elsewhere,
+    /// is forgotten instead of being shown.
+    unsafe fn menu_to_show(&mut self) -> Option<HMENU> {
+        if let Some(menu) = self.hpopupmenu {
+            if IsMenu(menu) != 0 {
+                return Some(menu);
+            }
+            log::warn!("the tray menu was destroyed while still set, not showing it");
+            self.hpopupmenu = None;
+        }
+        self.lazy_hpopupmenu()
+    }
+
+    /// Returns the menu built by `menu_fn`, building it first unless the cached one can be reused.
+    unsafe fn lazy_hpopupmenu(&mut self) -> Option<HMENU> {
+        let menu_fn = self.menu_fn.as_ref()?;
+        if self.lazy_menu.is_none() || !self.cache_menu_fn {
+            if let Some(menu) = self.lazy_menu.take() {
+                menu.detach_menu_subclass_from_hwnd(self.hwnd as _);
+            }
+            let menu = menu_fn();
+            menu.attach_menu_subclass_for_hwnd(self.hwnd as _);
+            self.lazy_menu = Some(menu);
+        }
+        self.lazy_menu.as_ref().map(|m| m.hpopupmenu() as _)
+    }
+}
+
+impl Drop for TrayUserData {
+    fn drop(&mut self) {
+        unsafe { remove_scroll_hook(self.hwnd) };
+        if let Some(menu) = &self.lazy_menu {
+            unsafe { menu.detach_menu_subclass_from_hwnd(self.hwnd as _) };
+        }
+    }
 }
 
 pub struct TrayIcon {
     hwnd: HWND,
     menu: Option<Box<dyn menu::ContextMenu>>,
     internal_id: u32,
+    guid: Option<u128>,
+    menu_opened_with: Rc<Cell<Option<MouseButton>>>,
+    tooltip: Option<String>,
+    title: Option<String>,
+    /// Lazily created window to own dialogs, see [`TrayIcon::owner_for_dialogs`].
+    dialog_owner: Option<HWND>,
+    /// Whether the tray is attached to a window we don't own, see [`TrayIcon::attach_to`].
+    attached: bool,
 }
 
 impl TrayIcon {
-    pub fn new(id: TrayIconId, attrs: TrayIconAttributes) -> crate::Result<Self> {
+    pub fn new(id: TrayIconId, mut attrs: TrayIconAttributes) -> crate::Result<Self> {
         let internal_id = COUNTER.next();
 
-        let class_name = util::encode_wide("tray_icon_app");
         unsafe {
             let hinstance = util::get_instance_handle();
+            let class_name = window_class(&TRAY_CLASS_NAME)?;
 
-            let wnd_class = WNDCLASSW {
-                lpfnWndProc: Some(tray_proc),
-                lpszClassName: class_name.as_ptr(),
-                hInstance: hinstance,
-                ..std::mem::zeroed()
-            };
-
-            RegisterClassW(&wnd_class);
+            let menu_opened_with = Rc::new(Cell::new(None));
 
-            let traydata = TrayUserData {
+            let traydata = Box::into_raw(Box::new(TrayUserData::new(
                 id,
                 internal_id,
-                hwnd: std::ptr::null_mut(),
-                hpopupmenu: attrs.menu.as_ref().map(|m| m.hpopupmenu() as _),
-                icon: attrs.icon.clone(),
-                tooltip: attrs.tooltip.clone(),
-                entered: false,
-                last_position: None,
-                menu_on_left_click: attrs.menu_on_left_click,
-            };
-
-            let hwnd = CreateWindowExW(
-                WS_EX_NOACTIVATE | WS_EX_TRANSPARENT | WS_EX_LAYERED |
+                std::ptr::null_mut(),
+                &mut attrs,
+                menu_opened_with.clone(),
+            )));
+
+            // create the window as per-monitor v2 DPI aware regardless of the process awareness,
+            // so the tray rect and click positions are always in physical pixels of the monitor
+            let hwnd = util::with_per_monitor_v2_dpi_awareness(|| {
+                CreateWindowExW(
+                    WS_EX_NOACTIVATE | WS_EX_TRANSPARENT | WS_EX_LAYERED |
             // WS_EX_TOOLWINDOW prevents this window from ever showing up in the taskbar, which
             // we want to avoid. If you remove this style, this window won't show up in the
             // taskbar *initially*, but it can show up at some later point. This can sometimes
@@ -107,26 +318,47 @@ impl TrayIcon {
             // `explorer.exe` and then starting the process back up.
             // It is unclear why the bug is triggered by waiting for several hours.
             WS_EX_TOOLWINDOW,
-                class_name.as_ptr(),
-                ptr::null(),
-                WS_OVERLAPPED,
-                CW_USEDEFAULT,
-                0,
-                CW_USEDEFAULT,
-                0,
-                std::ptr::null_mut(),
-                std::ptr::null_mut(),
-                hinstance,
-                Box::into_raw(Box::new(traydata)) as _,
-            );
+                    class_name,
+                    ptr::null(),
+                    WS_OVERLAPPED,
+                    CW_USEDEFAULT,
+                    0,
+                    CW_USEDEFAULT,
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    hinstance,
+                    traydata as _,
+                )
+            });
             if hwnd.is_null() {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+                return Err(crate::Error::WindowCreationFailed(tray_os_error(
+                    "window creation",
+                    internal_id,
+                )));
             }
 
             let hicon = attrs.icon.as_ref().map(|i| i.inner.as_raw_handle());
 
-            if !register_tray_icon(hwnd, internal_id, &hicon, &attrs.tooltip) {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+            let mut shell = Shell {
+                hwnd,
+                tray_id: internal_id,
+                guid: attrs.guid,
+                hicon: &hicon,
+                tooltip: &attrs.tooltip,
+            };
+            // the window owns `traydata` now and only frees it when destroyed
+            match register(&mut shell) {
+                Registration::Added { version_4 } => (*traydata).version_4 = version_4,
+                Registration::Failed => {
+                    let error =
+                        crate::Error::IconRegistrationFailed(tray_os_error("NIM_ADD", internal_id));
+                    DestroyWindow(hwnd);
+                    return Err(error);
+                }
+                Registration::Deferred => {
+                    log::warn!("the taskbar doesn't exist yet, adding the tray icon once it is");
+                }
             }
 
             if let Some(menu) = &attrs.menu {
@@ -136,38 +368,114 @@ impl TrayIcon {
             Ok(Self {
                 hwnd,
                 internal_id,
+                guid: attrs.guid,
                 menu: attrs.menu,
+                menu_opened_with,
+                tooltip: attrs.tooltip,
+                title: attrs.title,
+                dialog_owner: None,
+                attached: false,
             })
         }
     }
 
-    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<()> {
+    pub fn attach_to(
+        hwnd: isize,
+        id: TrayIconId,
+        mut attrs: TrayIconAttributes,
+    ) -> crate::Result<Self> {
+        let hwnd = hwnd as HWND;
+        let internal_id = COUNTER.next();
+
         unsafe {
-            let mut nid = NOTIFYICONDATAW {
-                uFlags: NIF_ICON,
-                hWnd: self.hwnd,
-                uID: self.internal_id,
-                ..std::mem::zeroed()
-            };
+            let mut existing = 0;
+            if GetWindowSubclass(
+                hwnd,
+                Some(tray_subclass_proc),
+                TRAY_SUBCLASS_ID,
+                &mut existing,
+            ) != 0
+            {
+                return Err(crate::Error::OsError(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "a tray icon is already attached to this window",
+                )));
+            }
+
+            let menu_opened_with = Rc::new(Cell::new(None));
+            let traydata = Box::into_raw(Box::new(TrayUserData::new(
+                id,
+                internal_id,
+                hwnd,
+                &mut attrs,
+                menu_opened_with.clone(),
+            )));
 
-            if let Some(hicon) = icon.as_ref().map(|i| i.inner.as_raw_handle()) {
-                nid.hIcon = hicon;
+            if SetWindowSubclass(
+                hwnd,
+                Some(tray_subclass_proc),
+                TRAY_SUBCLASS_ID,
+                traydata as _,
+            ) == 0
+            {
+                drop(Box::from_raw(traydata));
+                return Err(crate::Error::WindowCreationFailed(tray_os_error(
+                    "window subclassing",
+                    internal_id,
+                )));
+            }
+
+            let hicon = attrs.icon.as_ref().map(|i| i.inner.as_raw_handle());
+
+            let mut shell = Shell {
+                hwnd,
+                tray_id: internal_id,
+                guid: attrs.guid,
+                hicon: &hicon,
+                tooltip: &attrs.tooltip,
+            };
+            match register(&mut shell) {
+                Registration::Added { version_4 } => (*traydata).version_4 = version_4,
+                Registration::Failed => {
+                    let error =
+                        crate::Error::IconRegistrationFailed(tray_os_error("NIM_ADD", internal_id));
+                    RemoveWindowSubclass(hwnd, Some(tray_subclass_proc), TRAY_SUBCLASS_ID);
+                    drop(Box::from_raw(traydata));
+                    return Err(error);
+                }
+                Registration::Deferred => {
+                    log::warn!("the taskbar doesn't exist yet, adding the tray icon once it is");
+                }
             }
 
-            if Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == 0 {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+            if let Some(menu) = &attrs.menu {
+                menu.attach_menu_subclass_for_hwnd(hwnd as _);
             }
 
-            // send the new icon to the subclass proc to store it in the tray data
-            SendMessageW(
-                self.hwnd,
-                WM_USER_UPDATE_TRAYICON,
-                Box::into_raw(Box::new(icon)) as _,
-                0,
-            );
+            Ok(Self {
+                hwnd,
+                internal_id,
+                guid: attrs.guid,
+                menu: attrs.menu,
+                menu_opened_with,
+                tooltip: attrs.tooltip,
+                title: attrs.title,
+                dialog_owner: None,
+                attached: true,
+            })
         }
+    }
 
-        Ok(())
+    pub fn handle(&self) -> TrayIconHandle {
+        TrayIconHandle {
+            hwnd: self.hwnd as _,
+            internal_id: self.internal_id,
+            guid: self.guid,
+        }
+    }
+
+    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<()> {
+        self.handle().set_icon(icon)
     }
 
     pub fn set_menu(&mut self, menu: Option<Box<dyn menu::ContextMenu>>) {
@@ -181,47 +489,98 @@ impl TrayIcon {
 
         unsafe {
             // send the new menu to the subclass proc where we will update there
-            SendMessageW(
+            send_value(
                 self.hwnd,
                 WM_USER_UPDATE_TRAYMENU,
-                Box::into_raw(Box::new(menu.as_ref().map(|m| m.hpopupmenu()))) as _,
-                0,
+                menu.as_ref().map(|m| m.hpopupmenu()),
             );
         }
 
         self.menu = menu;
     }
 
+    pub fn refresh_menu_handle(&mut self) {
+        unsafe {
+            // only send the current menu handle to the subclass proc,
+            // the menu subclass is already attached
+            send_value(
+                self.hwnd,
+                WM_USER_UPDATE_TRAYMENU,
+                self.menu.as_ref().map(|m| m.hpopupmenu()),
+            );
+        }
+    }
+
     pub fn set_tooltip<S: AsRef<str>>(&mut self, tooltip: Option<S>) -> crate::Result<()> {
+        let tooltip = tooltip.map(|t| t.as_ref().to_string());
+        let result = self.handle().set_tooltip(tooltip.clone());
+        self.tooltip = tooltip;
+        result
+    }
+
+    pub fn tooltip(&self) -> Option<String> {
+        self.tooltip.clone()
+    }
+
+    pub fn set_tooltip_cycle(&mut self, tooltips: Vec<String>, interval: Duration) {
         unsafe {
-            let mut nid = NOTIFYICONDATAW {
-                uFlags: NIF_TIP,
-                hWnd: self.hwnd,
-                uID: self.internal_id,
-                ..std::mem::zeroed()
-            };
-            if let Some(tooltip) = &tooltip {
-                let tip = util::encode_wide(tooltip.as_ref());
-                #[allow(clippy::manual_memcpy)]
-                for i in 0..tip.len().min(128) {
-                    nid.szTip[i] = tip[i];
-                }
-            }
+            send_value(
+                self.hwnd,
+                WM_USER_UPDATE_TOOLTIP_CYCLE,
+                Some((tooltips, interval)),
+            );
+        }
+    }
 
-            if Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == 0 {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
-            }
+    pub fn stop_tooltip_cycle(&mut self) {
+        unsafe {
+            send_value(
+                self.hwnd,
+                WM_USER_UPDATE_TOOLTIP_CYCLE,
+                None::<(Vec<String>, Duration)>,
+            );
+        }
+    }
 
-            // send the new tooltip to the subclass proc to store it in the tray data
-            SendMessageW(
+    pub fn set_animation(&mut self, frames: Vec<Icon>, interval: Duration) {
+        unsafe {
+            send_value(
                 self.hwnd,
-                WM_USER_UPDATE_TRAYTOOLTIP,
-                Box::into_raw(Box::new(tooltip.map(|t| t.as_ref().to_string()))) as _,
-                0,
+                WM_USER_UPDATE_ANIMATION,
+                Some((frames, interval)),
             );
         }
+    }
 
-        Ok(())
+    pub fn stop_animation(&mut self) {
+        unsafe {
+            send_value(
+                self.hwnd,
+                WM_USER_UPDATE_ANIMATION,
+                None::<(Vec<Icon>, Duration)>,
+            );
+        }
+    }
+
+    pub fn animate(&mut self, frames: Vec<Icon>, interval: Duration) -> AnimationHandle {
+        let generation = unsafe {
+            send_value(
+                self.hwnd,
+                WM_USER_UPDATE_ANIMATION,
+                Some((frames, interval)),
+            )
+        };
+        AnimationHandle {
+            hwnd: self.hwnd as _,
+            generation: generation as _,
+        }
+    }
+
+    pub fn set_hover_icon(&mut self, icon: Option<Icon>) {
+        unsafe {
+            // send the new hover icon to the subclass proc to store it in the tray data
+            send_value(self.hwnd, WM_USER_UPDATE_HOVER_ICON, icon);
+        }
     }
 
     pub fn set_show_menu_on_left_click(&mut self, enable: bool) {
@@ -235,45 +594,353 @@ impl TrayIcon {
         }
     }
 
-    pub fn set_title<S: AsRef<str>>(&mut self, _title: Option<S>) {}
+    pub fn set_menu_enabled(&mut self, enabled: bool) {
+        unsafe {
+            SendMessageW(self.hwnd, WM_USER_SET_MENU_ENABLED, enabled as usize, 0);
+        }
+    }
+
+    pub fn set_menu_alignment(&mut self, alignment: MenuAlignment) {
+        unsafe {
+            send_value(self.hwnd, WM_USER_UPDATE_MENU_ALIGNMENT, alignment);
+        }
+    }
+
+    pub fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) {
+        self.title = title.map(|s| s.as_ref().to_string());
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.title.clone()
+    }
 
     pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
-        unsafe {
-            SendMessageW(
-                self.hwnd,
-                if visible {
-                    WM_USER_SHOW_TRAYICON
-                } else {
-                    WM_USER_HIDE_TRAYICON
-                },
-                0,
-                0,
-            );
+        self.handle().set_visible(visible)
+    }
+
+    pub fn show_notification(
+        &self,
+        title: &str,
+        body: &str,
+        icon: NotificationIcon,
+    ) -> crate::Result<()> {
+        let mut nid =
+            notification::notification_data(self.hwnd, self.internal_id, title, body, icon);
+        set_guid(&mut nid, self.guid);
+        if unsafe { Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) } == FALSE {
+            return Err(tray_error("NIM_MODIFY", self.internal_id));
         }
+        Ok(())
+    }
 
+    pub fn show_menu(&mut self, position: Option<PhysicalPosition<f64>>) -> crate::Result<()> {
+        let shown = unsafe { send_value(self.hwnd, WM_USER_SHOW_MENU, position) };
+        if shown == 0 {
+            return Err(crate::Error::NoMenu);
+        }
         Ok(())
     }
 
+    pub fn show_menu_on_monitor(
+        &mut self,
+        monitor: isize,
+        position: Option<PhysicalPosition<f64>>,
+    ) -> crate::Result<()> {
+        let position = match position {
+            Some(position) => position,
+            None => {
+                let mut cursor = POINT { x: 0, y: 0 };
+                if unsafe { get_cursor_pos(&mut cursor) } == 0 {
+                    return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+                }
+                PhysicalPosition::new(cursor.x as f64, cursor.y as f64)
+            }
+        };
+        let position = position::clamp_to_work_area(position, monitor as _)?;
+        self.show_menu(Some(position))
+    }
+
+    pub fn remove(&mut self) -> crate::Result<()> {
+        unsafe { remove_tray_icon(self.hwnd, self.internal_id, self.guid) }
+    }
+
+    pub fn menu_opened_with(&self) -> Option<MouseButton> {
+        self.menu_opened_with.get()
+    }
+
     pub fn rect(&self) -> Option<Rect> {
-        get_tray_rect(self.internal_id, self.hwnd).map(Into::into)
+        let rect = get_tray_rect(self.internal_id, self.hwnd, self.guid)?;
+        // the icon is off-screen while an auto-hidden taskbar is slid out of view
+        let rect = if taskbar_autohidden() {
+            position::clamp_to_monitor(rect)
+        } else {
+            rect
+        };
+        Some(rect.into())
+    }
+
+    pub fn scale_factor(&self) -> Option<f64> {
+        let rect = get_tray_rect(self.internal_id, self.hwnd, self.guid)?;
+        Some(position::monitor_dpi(rect) as f64 / USER_DEFAULT_SCREEN_DPI as f64)
+    }
+
+    pub fn hwnd(&self) -> isize {
+        self.hwnd as _
+    }
+
+    pub fn owner_for_dialogs(&mut self) -> crate::Result<isize> {
+        let owner = match self.dialog_owner {
+            Some(owner) => owner,
+            None => {
+                let owner = create_dialog_owner()?;
+                self.dialog_owner = Some(owner);
+                owner
+            }
+        };
+
+        unsafe {
+            // move the owner to the tray icon so dialogs open on the taskbar's monitor
+            if let Some(rect) = get_tray_rect(self.internal_id, self.hwnd, self.guid) {
+                SetWindowPos(
+                    owner,
+                    std::ptr::null_mut(),
+                    rect.left + (rect.right - rect.left) / 2,
+                    rect.top + (rect.bottom - rect.top) / 2,
+                    0,
+                    0,
+                    SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+            }
+
+            // dialogs owned by a background window open behind other windows
+            SetForegroundWindow(owner);
+        }
+
+        Ok(owner as _)
+    }
+}
+
+/// Stops the animation started with [`TrayIcon::animate`] from any thread, unless another
+/// animation was started since.
+///
+/// The stop message is posted rather than sent, so it doesn't block a thread the tray thread
+/// may be waiting on, e.g. while joining it.
+pub struct AnimationHandle {
+    hwnd: isize,
+    generation: usize,
+}
+
+impl AnimationHandle {
+    pub fn stop(&self) {
+        let hwnd = self.hwnd as HWND;
+        unsafe {
+            if IsWindow(hwnd) == TRUE {
+                PostMessageW(hwnd, WM_USER_STOP_ANIMATION, self.generation, 0);
+            }
+        }
+    }
+}
+
+/// Updates a tray icon from any thread, by sending the same messages as [`TrayIcon`].
+///
+/// The window is kept as an `isize` so the handle is `Send` and `Sync`. `SendMessageW` blocks
+/// until the thread that created the tray icon handles the message, and fails once the window
+/// is destroyed, in which case the sent values are dropped.
+#[derive(Clone)]
+pub struct TrayIconHandle {
+    hwnd: isize,
+    internal_id: u32,
+    guid: Option<u128>,
+}
+
+impl TrayIconHandle {
+    pub fn set_icon(&self, icon: Option<Icon>) -> crate::Result<()> {
+        let hwnd = self.hwnd as HWND;
+        unsafe {
+            if !modify_tray_icon(hwnd, self.internal_id, self.guid, icon.as_ref()) {
+                return Err(tray_error("NIM_MODIFY", self.internal_id));
+            }
+
+            // send the new icon to the subclass proc to store it in the tray data
+            send_value(hwnd, WM_USER_UPDATE_TRAYICON, icon);
+        }
+
+        Ok(())
+    }
+
+    pub fn set_tooltip(&self, tooltip: Option<String>) -> crate::Result<()> {
+        let hwnd = self.hwnd as HWND;
+        unsafe {
+            let result = modify_tray_tooltip(hwnd, self.internal_id, self.guid, tooltip.as_deref());
+            let error = tray_error("NIM_MODIFY", self.internal_id);
+
+            // send the new tooltip to the subclass proc to store it in the tray data,
+            // even if modifying failed, so it is used when the icon is re-registered
+            // after the taskbar restarts
+            send_value(hwnd, WM_USER_UPDATE_TRAYTOOLTIP, tooltip);
+
+            if !result {
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_visible(&self, visible: bool) -> crate::Result<()> {
+        let (msg, operation) = if visible {
+            (WM_USER_SHOW_TRAYICON, "NIM_ADD")
+        } else {
+            (WM_USER_HIDE_TRAYICON, "NIM_DELETE")
+        };
+
+        // the message is always handled while the window exists
+        if unsafe { SendMessageW(self.hwnd as HWND, msg, 0, 0) } == 0 {
+            return Err(tray_error(operation, self.internal_id));
+        }
+
+        Ok(())
     }
 }
 
 impl Drop for TrayIcon {
     fn drop(&mut self) {
         unsafe {
-            remove_tray_icon(self.hwnd, self.internal_id);
+            let _ = remove_tray_icon(self.hwnd, self.internal_id, self.guid);
 
             if let Some(menu) = &self.menu {
                 menu.detach_menu_subclass_from_hwnd(self.hwnd as _);
             }
 
-            // destroy the hidden window used by the tray
-            DestroyWindow(self.hwnd);
+            if let Some(owner) = self.dialog_owner {
+                DestroyWindow(owner);
+            }
+
+            if self.attached {
+                // the window isn't ours, only remove our subclass and free its data
+                let mut traydata = 0;
+                if GetWindowSubclass(
+                    self.hwnd,
+                    Some(tray_subclass_proc),
+                    TRAY_SUBCLASS_ID,
+                    &mut traydata,
+                ) != 0
+                {
+                    KillTimer(self.hwnd, WM_USER_LEAVE_TIMER_ID as _);
+                    KillTimer(self.hwnd, WM_USER_TOOLTIP_CYCLE_TIMER_ID as _);
+                    KillTimer(self.hwnd, WM_USER_ANIMATION_TIMER_ID as _);
+                    RemoveWindowSubclass(self.hwnd, Some(tray_subclass_proc), TRAY_SUBCLASS_ID);
+                    drop(Box::from_raw(traydata as *mut TrayUserData));
+                }
+            } else {
+                // destroy the hidden window used by the tray
+                DestroyWindow(self.hwnd);
+            }
+        }
+    }
+}
+
+/// An OS error annotated with the tray operation that failed.
+#[derive(Debug, thiserror::Error)]
+#[error("failed {operation} for icon {tray_id}: {source}")]
+struct TrayOperationError {
+    operation: &'static str,
+    tray_id: u32,
+    #[source]
+    source: std::io::Error,
+}
+
+/// Wraps the last OS error with the failed `operation`, so it is shown when the error is displayed.
+fn tray_os_error(operation: &'static str, tray_id: u32) -> std::io::Error {
+    let source = std::io::Error::last_os_error();
+    std::io::Error::new(
+        source.kind(),
+        TrayOperationError {
+            operation,
+            tray_id,
+            source,
+        },
+    )
+}
+
+/// Same as [`tray_os_error`] for operations without a dedicated [`crate::Error`] variant.
+fn tray_error(operation: &'static str, tray_id: u32) -> crate::Error {
+    crate::Error::OsError(tray_os_error(operation, tray_id))
+}
+
+/// Registers a window class, returning its name or the OS error code if it failed.
+///
+/// Classes are process-global, so this is only called once per class through a [`Lazy`].
+/// The name includes the crate version so different versions of this crate in the same
+/// process don't share a class with a different window procedure.
+unsafe fn register_window_class(name: &str, wndproc: WNDPROC) -> Result<Vec<u16>, i32> {
+    let class_name = util::encode_wide(name);
+    let wnd_class = WNDCLASSW {
+        lpfnWndProc: wndproc,
+        lpszClassName: class_name.as_ptr(),
+        hInstance: util::get_instance_handle(),
+        ..std::mem::zeroed()
+    };
+
+    if RegisterClassW(&wnd_class) == 0 {
+        return Err(GetLastError() as i32);
+    }
+    Ok(class_name)
+}
+
+/// An OS error from registering a window class, see [`register_window_class`].
+#[derive(Debug, thiserror::Error)]
+#[error("failed to register the window class: {source}")]
+struct WindowClassError {
+    #[source]
+    source: std::io::Error,
+}
+
+/// Returns the name of a class registered with [`register_window_class`],
+/// or the error of its registration.
+fn window_class(class: &Lazy<Result<Vec<u16>, i32>>) -> crate::Result<*const u16> {
+    match &**class {
+        Ok(name) => Ok(name.as_ptr()),
+        Err(code) => {
+            let source = std::io::Error::from_raw_os_error(*code);
+            Err(crate::Error::WindowCreationFailed(std::io::Error::new(
+                source.kind(),
+                WindowClassError { source },
+            )))
         }
     }
 }
 
+/// Creates an invisible, zero-size, top-level window that can own dialogs,
+/// unlike the tray window which can't be activated.
+fn create_dialog_owner() -> crate::Result<HWND> {
+    unsafe {
+        let hinstance = util::get_instance_handle();
+        let class_name = window_class(&DIALOG_OWNER_CLASS_NAME)?;
+
+        let hwnd = CreateWindowExW(
+            // keep it out of the taskbar
+            WS_EX_TOOLWINDOW,
+            class_name,
+            ptr::null(),
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            hinstance,
+            std::ptr::null(),
+        );
+        if hwnd.is_null() {
+            return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+        }
+
+        Ok(hwnd)
+    }
+}
+
 unsafe extern "system" fn tray_proc(
     hwnd: HWND,
     msg: u32,
@@ -297,48 +964,211 @@ unsafe extern "system" fn tray_proc(
         _ => userdata_ptr as *mut TrayUserData,
     };
 
+    if msg == WM_DESTROY {
+        drop(Box::from_raw(userdata_ptr));
+        return 0;
+    }
+
     let userdata = &mut *(userdata_ptr);
+    handle_tray_message(userdata, hwnd, msg, wparam, lparam)
+        .unwrap_or_else(|| DefWindowProcW(hwnd, msg, wparam, lparam))
+}
 
-    match msg {
-        WM_DESTROY => {
-            drop(Box::from_raw(userdata_ptr));
-            return 0;
-        }
-        WM_USER_UPDATE_TRAYMENU => {
-            let hpopupmenu = Box::from_raw(wparam as *mut Option<isize>);
-            userdata.hpopupmenu = (*hpopupmenu).map(|h| h as *mut _);
-        }
-        WM_USER_UPDATE_TRAYICON => {
-            let icon = Box::from_raw(wparam as *mut Option<Icon>);
-            userdata.icon = *icon;
-        }
-        WM_USER_SHOW_TRAYICON => {
-            register_tray_icon(
-                userdata.hwnd,
-                userdata.internal_id,
-                &userdata.icon.as_ref().map(|i| i.inner.as_raw_handle()),
-                &userdata.tooltip,
-            );
-        }
-        WM_USER_HIDE_TRAYICON => {
-            remove_tray_icon(userdata.hwnd, userdata.internal_id);
-        }
-        WM_USER_UPDATE_TRAYTOOLTIP => {
-            let tooltip = Box::from_raw(wparam as *mut Option<String>);
-            userdata.tooltip = *tooltip;
+/// Window subclass procedure used when the tray icon is attached to a window we don't own.
+unsafe extern "system" fn tray_subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _uidsubclass: usize,
+    dwrefdata: usize,
+) -> LRESULT {
+    let userdata = &mut *(dwrefdata as *mut TrayUserData);
+    handle_tray_message(userdata, hwnd, msg, wparam, lparam)
+        .unwrap_or_else(|| DefSubclassProc(hwnd, msg, wparam, lparam))
+}
+
+/// Handles the tray messages shared by [`tray_proc`] and [`tray_subclass_proc`].
+///
+/// Returns `Some` when the message was fully handled and shouldn't be
+/// passed to the next window procedure.
+unsafe fn handle_tray_message(
+    userdata: &mut TrayUserData,
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> Option<LRESULT> {
+    // `NOTIFYICON_VERSION_4` messages have the notification in the low word of `lparam`,
+    // and the position of the event in screen coordinates in `wparam`
+    let (lparam, anchor) = if msg == WM_USER_TRAYICON && userdata.version_4 {
+        let anchor = POINT {
+            x: (wparam & 0xFFFF) as u16 as i16 as i32,
+            y: ((wparam >> 16) & 0xFFFF) as u16 as i16 as i32,
+        };
+        (lparam & 0xFFFF, Some(anchor))
+    } else {
+        (lparam, None)
+    };
+
+    // the shell sends `WM_CONTEXTMENU` both when the menu key is pressed and after a right click,
+    // but the menu of the latter was already shown for `WM_RBUTTONDOWN`
+    let keyboard_context_menu = msg == WM_USER_TRAYICON
+        && lparam as u32 == WM_CONTEXTMENU
+        && !std::mem::take(&mut userdata.right_button_released);
+
+    match msg {
+        WM_USER_UPDATE_TRAYMENU => {
+            let hpopupmenu = take_sent::<Option<isize>>(wparam);
+            userdata.hpopupmenu = hpopupmenu.map(|h| h as *mut _);
         }
-        _ if msg == *S_U_TASKBAR_RESTART => {
-            remove_tray_icon(userdata.hwnd, userdata.internal_id);
-            register_tray_icon(
+        WM_USER_UPDATE_TRAYICON => {
+            userdata.icon = take_sent(wparam);
+        }
+        WM_USER_SHOW_TRAYICON => {
+            let added = register_tray_icon(
                 userdata.hwnd,
                 userdata.internal_id,
-                &userdata.icon.as_ref().map(|i| i.inner.as_raw_handle()),
+                userdata.guid,
+                &userdata.shown_icon().map(|i| i.inner.as_raw_handle()),
                 &userdata.tooltip,
             );
+            if added {
+                userdata.version_4 =
+                    set_tray_version(userdata.hwnd, userdata.internal_id, userdata.guid);
+            }
+            // tells `TrayIconHandle::set_visible` the window still exists
+            return Some(1);
+        }
+        WM_USER_HIDE_TRAYICON => {
+            let _ = remove_tray_icon(userdata.hwnd, userdata.internal_id, userdata.guid);
+            return Some(1);
+        }
+        WM_USER_UPDATE_TRAYTOOLTIP => {
+            userdata.tooltip = take_sent(wparam);
+        }
+        WM_USER_UPDATE_TOOLTIP_CYCLE => {
+            let cycle = take_sent::<Option<(Vec<String>, Duration)>>(wparam);
+            KillTimer(hwnd, WM_USER_TOOLTIP_CYCLE_TIMER_ID as _);
+            userdata.tooltip_cycle_index = 0;
+
+            match cycle {
+                Some((tooltips, interval)) if !tooltips.is_empty() => {
+                    modify_tray_tooltip(
+                        hwnd,
+                        userdata.internal_id,
+                        userdata.guid,
+                        Some(&tooltips[0]),
+                    );
+                    userdata.tooltip_cycle = tooltips;
+                    let interval = interval.as_millis().clamp(1, u32::MAX as u128) as u32;
+                    SetTimer(
+                        hwnd,
+                        WM_USER_TOOLTIP_CYCLE_TIMER_ID as _,
+                        interval,
+                        Some(tray_timer_proc),
+                    );
+                }
+                _ => {
+                    // restore the tooltip set with `set_tooltip`
+                    userdata.tooltip_cycle.clear();
+                    modify_tray_tooltip(
+                        hwnd,
+                        userdata.internal_id,
+                        userdata.guid,
+                        userdata.tooltip.as_deref(),
+                    );
+                }
+            }
+        }
+        WM_TIMER if wparam as u32 == WM_USER_TOOLTIP_CYCLE_TIMER_ID => {
+            if !userdata.tooltip_cycle.is_empty() {
+                userdata.tooltip_cycle_index =
+                    (userdata.tooltip_cycle_index + 1) % userdata.tooltip_cycle.len();
+                let tooltip = &userdata.tooltip_cycle[userdata.tooltip_cycle_index];
+                modify_tray_tooltip(hwnd, userdata.internal_id, userdata.guid, Some(tooltip));
+            }
+            return Some(0);
+        }
+        WM_USER_UPDATE_ANIMATION => {
+            let animation = take_sent::<Option<(Vec<Icon>, Duration)>>(wparam);
+            return Some(userdata.set_animation(animation) as _);
+        }
+        WM_USER_STOP_ANIMATION if wparam == userdata.animation_generation => {
+            userdata.set_animation(None);
+        }
+        WM_TIMER if wparam as u32 == WM_USER_ANIMATION_TIMER_ID => {
+            if !userdata.animation.is_empty() {
+                userdata.animation_index =
+                    (userdata.animation_index + 1) % userdata.animation.len();
+                if !(userdata.entered && userdata.hover_icon.is_some()) {
+                    let icon = userdata.shown_icon();
+                    modify_tray_icon(hwnd, userdata.internal_id, userdata.guid, icon);
+                }
+            }
+            return Some(0);
+        }
+        _ if msg == *S_U_TASKBAR_RESTART => {
+            let hicon = userdata.shown_icon().map(|i| i.inner.as_raw_handle());
+            let mut shell = Shell {
+                hwnd: userdata.hwnd,
+                tray_id: userdata.internal_id,
+                guid: userdata.guid,
+                hicon: &hicon,
+                tooltip: &userdata.tooltip,
+            };
+            if let Some(version_4) = reregister(&mut shell) {
+                userdata.version_4 = version_4;
+            }
         }
         WM_USER_SHOW_MENU_ON_LEFT_CLICK => {
             userdata.menu_on_left_click = wparam != 0;
         }
+        WM_USER_SET_MENU_ENABLED => {
+            userdata.menu_enabled = wparam != 0;
+        }
+        WM_USER_UPDATE_MENU_ALIGNMENT => {
+            userdata.menu_alignment = take_sent(wparam);
+        }
+        WM_USER_SHOW_MENU => {
+            let position = take_sent::<Option<PhysicalPosition<f64>>>(wparam);
+            let mut cursor = POINT { x: 0, y: 0 };
+            match position {
+                Some(position) => {
+                    cursor.x = position.x.round() as i32;
+                    cursor.y = position.y.round() as i32;
+                }
+                None => {
+                    get_cursor_pos(&mut cursor);
+                }
+            }
+
+            let Some(menu) = userdata.menu_to_show() else {
+                return Some(0);
+            };
+            // no mouse button opened this menu
+            userdata.menu_opened_with.set(None);
+            crate::set_menu_owner(&userdata.id);
+            show_tray_menu(
+                hwnd,
+                menu,
+                cursor.x,
+                cursor.y,
+                true,
+                userdata.menu_alignment,
+                &userdata.menu_theme,
+            );
+            return Some(1);
+        }
+        WM_USER_UPDATE_HOVER_ICON => {
+            userdata.hover_icon = take_sent(wparam);
+
+            // apply the change right away if the cursor is already over the icon
+            if userdata.entered {
+                let icon = userdata.hover_icon.as_ref().or(userdata.shown_icon());
+                modify_tray_icon(hwnd, userdata.internal_id, userdata.guid, icon);
+            }
+        }
 
         WM_USER_TRAYICON
             if matches!(
@@ -356,16 +1186,18 @@ unsafe extern "system" fn tray_proc(
             ) =>
         {
             let mut cursor = POINT { x: 0, y: 0 };
-            if GetCursorPos(&mut cursor as _) == 0 {
-                return 0;
+            if let Some(anchor) = anchor {
+                cursor = anchor;
+            } else if get_cursor_pos(&mut cursor) == 0 {
+                return Some(0);
             }
 
             let id = userdata.id.clone();
             let position = PhysicalPosition::new(cursor.x as f64, cursor.y as f64);
 
-            let rect = match get_tray_rect(userdata.internal_id, hwnd) {
+            let rect = match get_tray_rect(userdata.internal_id, hwnd, userdata.guid) {
                 Some(rect) => Rect::from(rect),
-                None => return 0,
+                None => return Some(0),
             };
 
             let event = match lparam as u32 {
@@ -431,6 +1263,24 @@ unsafe extern "system" fn tray_proc(
                 },
                 WM_MOUSEMOVE if !userdata.entered => {
                     userdata.entered = true;
+                    userdata.last_position = Some(position);
+                    // Start checking if cursor left right away, so we don't miss it
+                    // if no more WM_MOUSEMOVE events arrive
+                    SetTimer(hwnd, WM_USER_LEAVE_TIMER_ID as _, 15, Some(tray_timer_proc));
+
+                    if userdata.event_mask.contains(EventMask::SCROLL) {
+                        set_scroll_hook(hwnd);
+                    }
+
+                    if let Some(hover_icon) = &userdata.hover_icon {
+                        modify_tray_icon(
+                            hwnd,
+                            userdata.internal_id,
+                            userdata.guid,
+                            Some(hover_icon),
+                        );
+                    }
+
                     TrayIconEvent::Enter { id, rect, position }
                 }
                 WM_MOUSEMOVE if userdata.entered => {
@@ -443,43 +1293,253 @@ unsafe extern "system" fn tray_proc(
 
                         TrayIconEvent::Move { id, rect, position }
                     } else {
-                        return 0;
+                        return Some(0);
                     }
                 }
 
                 _ => unreachable!(),
             };
 
-            TrayIconEvent::send(event);
+            if userdata.event_mask.allows(&event) {
+                TrayIconEvent::send(event);
+            }
 
-            if lparam as u32 == WM_RBUTTONDOWN
-                || (userdata.menu_on_left_click && lparam as u32 == WM_LBUTTONDOWN)
-            {
-                if let Some(menu) = userdata.hpopupmenu {
-                    show_tray_menu(hwnd, menu, cursor.x, cursor.y);
+            if lparam as u32 == WM_RBUTTONUP {
+                userdata.right_button_released = true;
+            }
+
+            // only the next button press can be the one that dismissed the menu
+            let dismissed_menu = matches!(
+                lparam as u32,
+                WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN
+            ) && std::mem::take(&mut userdata.menu_dismissed_by_icon_click);
+
+            let menu_button = match lparam as u32 {
+                _ if !userdata.menu_enabled => None,
+                WM_RBUTTONDOWN => Some(MouseButton::Right),
+                WM_LBUTTONDOWN if userdata.menu_on_left_click => Some(MouseButton::Left),
+                _ => None,
+            };
+            // only build a lazy menu when it is about to be shown
+            let menu = match (menu_button, dismissed_menu) {
+                (Some(_), false) => userdata.menu_to_show(),
+                _ => None,
+            };
+            if let (Some(button), Some(menu)) = (menu_button, menu) {
+                // remember which button opened the menu, so it can be queried
+                // while handling the menu events that follow.
+                userdata.menu_opened_with.set(Some(button));
+                crate::set_menu_owner(&userdata.id);
+                let set_foreground =
+                    button != MouseButton::Left || userdata.left_click_menu_foreground;
+                show_tray_menu(
+                    hwnd,
+                    menu,
+                    cursor.x,
+                    cursor.y,
+                    set_foreground,
+                    userdata.menu_alignment,
+                    &userdata.menu_theme,
+                );
+
+                if userdata.menu_toggle_on_click {
+                    userdata.menu_dismissed_by_icon_click =
+                        is_tray_icon_pressed(hwnd, userdata.internal_id, userdata.guid);
                 }
+            } else if userdata.context_requested_events
+                && lparam as u32 == WM_RBUTTONDOWN
+                && !dismissed_menu
+            {
+                TrayIconEvent::send(TrayIconEvent::ContextRequested {
+                    id: userdata.id.clone(),
+                    position,
+                    rect,
+                });
             }
         }
 
-        WM_TIMER if wparam as u32 == WM_USER_LEAVE_TIMER_ID => {
-            if let Some(position) = userdata.last_position.take() {
-                let mut cursor = POINT { x: 0, y: 0 };
-                if GetCursorPos(&mut cursor as _) == 0 {
-                    return 0;
-                }
+        WM_USER_SCROLL => {
+            // the wheel delta is in the high word of `mouseData`, in multiples of `WHEEL_DELTA`
+            let delta = (lparam as u32 >> 16) as u16 as i16 as f64 / WHEEL_DELTA as f64;
+            let (delta_x, delta_y) = if wparam as u32 == WM_MOUSEHWHEEL {
+                (delta, 0.)
+            } else {
+                (0., delta)
+            };
+            let Some((direction, delta)) = ScrollDirection::from_deltas(delta_x, delta_y) else {
+                return Some(0);
+            };
 
-                let rect = match get_tray_rect(userdata.internal_id, hwnd) {
-                    Some(r) => r,
-                    None => return 0,
+            let mut cursor = POINT { x: 0, y: 0 };
+            if get_cursor_pos(&mut cursor) == 0 {
+                return Some(0);
+            }
+            let Some(rect) = get_tray_rect(userdata.internal_id, hwnd, userdata.guid) else {
+                return Some(0);
+            };
+
+            let event = TrayIconEvent::Scroll {
+                id: userdata.id.clone(),
+                position: PhysicalPosition::new(cursor.x as f64, cursor.y as f64),
+                rect: rect.into(),
+                direction,
+                delta,
+            };
+            if userdata.event_mask.allows(&event) {
+                TrayIconEvent::send(event);
+            }
+            return Some(0);
+        }
+
+        WM_USER_TRAYICON if matches!(lparam as u32, NIN_SELECT | NIN_KEYSELECT) => {
+            let mut cursor = POINT { x: 0, y: 0 };
+            if let Some(anchor) = anchor {
+                cursor = anchor;
+            } else if get_cursor_pos(&mut cursor) == 0 {
+                return Some(0);
+            }
+            let Some(rect) = get_tray_rect(userdata.internal_id, hwnd, userdata.guid) else {
+                return Some(0);
+            };
+
+            let event = TrayIconEvent::Activate {
+                id: userdata.id.clone(),
+                position: PhysicalPosition::new(cursor.x as f64, cursor.y as f64),
+                rect: rect.into(),
+                keyboard: lparam as u32 == NIN_KEYSELECT,
+            };
+            if userdata.event_mask.allows(&event) {
+                TrayIconEvent::send(event);
+            }
+
+            // the shell sends no button messages when the icon is activated with the keyboard
+            let menu = match lparam as u32 == NIN_KEYSELECT
+                && userdata.menu_enabled
+                && userdata.menu_on_left_click
+            {
+                true => userdata.menu_to_show(),
+                false => None,
+            };
+            if let Some(menu) = menu {
+                userdata.menu_opened_with.set(None);
+                crate::set_menu_owner(&userdata.id);
+                show_tray_menu(
+                    hwnd,
+                    menu,
+                    cursor.x,
+                    cursor.y,
+                    true,
+                    userdata.menu_alignment,
+                    &userdata.menu_theme,
+                );
+                set_tray_focus(hwnd, userdata.internal_id, userdata.guid);
+            }
+        }
+
+        WM_USER_TRAYICON if keyboard_context_menu => {
+            let mut cursor = POINT { x: 0, y: 0 };
+            if let Some(anchor) = anchor {
+                cursor = anchor;
+            } else if get_cursor_pos(&mut cursor) == 0 {
+                return Some(0);
+            }
+
+            let menu = match userdata.menu_enabled {
+                true => userdata.menu_to_show(),
+                false => None,
+            };
+            if let Some(menu) = menu {
+                // no mouse button opened this menu
+                userdata.menu_opened_with.set(None);
+                crate::set_menu_owner(&userdata.id);
+                show_tray_menu(
+                    hwnd,
+                    menu,
+                    cursor.x,
+                    cursor.y,
+                    true,
+                    userdata.menu_alignment,
+                    &userdata.menu_theme,
+                );
+                // let keyboard users keep navigating the notification area
+                set_tray_focus(hwnd, userdata.internal_id, userdata.guid);
+            } else if userdata.context_requested_events {
+                let Some(rect) = get_tray_rect(userdata.internal_id, hwnd, userdata.guid) else {
+                    return Some(0);
                 };
+                TrayIconEvent::send(TrayIconEvent::ContextRequested {
+                    id: userdata.id.clone(),
+                    position: PhysicalPosition::new(cursor.x as f64, cursor.y as f64),
+                    rect: rect.into(),
+                });
+            }
+        }
+
+        WM_USER_TRAYICON
+            if matches!(
+                lparam as u32,
+                NIN_BALLOONSHOW | NIN_BALLOONHIDE | NIN_BALLOONUSERCLICK | NIN_BALLOONTIMEOUT
+            ) =>
+        {
+            let state = match lparam as u32 {
+                NIN_BALLOONSHOW => NotificationState::Shown,
+                NIN_BALLOONHIDE => NotificationState::Hidden,
+                NIN_BALLOONUSERCLICK => NotificationState::Clicked,
+                _ => NotificationState::TimedOut,
+            };
+            let event = TrayIconEvent::Notification {
+                id: userdata.id.clone(),
+                state,
+            };
+            if userdata.event_mask.allows(&event) {
+                TrayIconEvent::send(event);
+            }
+        }
+
+        // forward tray messages we don't model, e.g. `NIN_*` notifications, if requested
+        WM_USER_TRAYICON if userdata.raw_events => {
+            TrayIconEvent::send(TrayIconEvent::Raw {
+                id: userdata.id.clone(),
+                code: lparam as u32,
+            });
+        }
+
+        WM_TIMER if wparam as u32 == WM_USER_LEAVE_TIMER_ID => {
+            let mut cursor = POINT { x: 0, y: 0 };
+            if get_cursor_pos(&mut cursor) == 0 {
+                return Some(0);
+            }
 
-                let in_x = (rect.left..rect.right).contains(&cursor.x);
-                let in_y = (rect.top..rect.bottom).contains(&cursor.y);
+            // Keep checking even if no WM_MOUSEMOVE arrived since the last tick,
+            // so a missed leave doesn't leave the hover icon shown.
+            let position = userdata
+                .last_position
+                .take()
+                .unwrap_or_else(|| PhysicalPosition::new(cursor.x as f64, cursor.y as f64));
 
-                if !in_x || !in_y {
-                    KillTimer(hwnd, WM_USER_LEAVE_TIMER_ID as _);
-                    userdata.entered = false;
+            let rect = match get_tray_rect(userdata.internal_id, hwnd, userdata.guid) {
+                Some(r) => r,
+                None => return Some(0),
+            };
 
+            let in_x = (rect.left..rect.right).contains(&cursor.x);
+            let in_y = (rect.top..rect.bottom).contains(&cursor.y);
+
+            if !in_x || !in_y {
+                KillTimer(hwnd, WM_USER_LEAVE_TIMER_ID as _);
+                userdata.entered = false;
+                remove_scroll_hook(hwnd);
+
+                if userdata.hover_icon.is_some() {
+                    modify_tray_icon(
+                        hwnd,
+                        userdata.internal_id,
+                        userdata.guid,
+                        userdata.shown_icon(),
+                    );
+                }
+
+                if userdata.event_mask.contains(EventMask::HOVER) {
                     TrayIconEvent::send(TrayIconEvent::Leave {
                         id: userdata.id.clone(),
                         rect: rect.into(),
@@ -488,40 +1548,166 @@ unsafe extern "system" fn tray_proc(
                 }
             }
 
-            return 0;
+            return Some(0);
         }
 
         _ => {}
     }
 
-    DefWindowProcW(hwnd, msg, wparam, lparam)
+    None
+}
+
+/// Whether a mouse button is held down while the cursor is over the tray icon.
+unsafe fn is_tray_icon_pressed(hwnd: HWND, tray_id: u32, guid: Option<u128>) -> bool {
+    // physical buttons are checked, so swapped mouse buttons don't matter
+    let pressed = [VK_LBUTTON, VK_RBUTTON, VK_MBUTTON]
+        .into_iter()
+        .any(|vk| GetAsyncKeyState(vk as _) as u16 & 0x8000 != 0);
+    if !pressed {
+        return false;
+    }
+
+    let mut cursor = POINT { x: 0, y: 0 };
+    if get_cursor_pos(&mut cursor) == 0 {
+        return false;
+    }
+
+    get_tray_rect(tray_id, hwnd, guid).is_some_and(|rect| {
+        (rect.left..rect.right).contains(&cursor.x) && (rect.top..rect.bottom).contains(&cursor.y)
+    })
 }
 
-unsafe extern "system" fn tray_timer_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: u32) {
-    tray_proc(hwnd, msg, wparam, lparam as _);
+/// Installs a low-level mouse hook that forwards wheel messages to `hwnd` as [`WM_USER_SCROLL`],
+/// since the shell doesn't forward them to tray icons.
+///
+/// Only one hook is installed per thread, since the cursor can only be over one tray icon.
+unsafe fn set_scroll_hook(hwnd: HWND) {
+    if let Some((hook, _)) = SCROLL_HOOK.take() {
+        UnhookWindowsHookEx(hook);
+    }
+    let hook = SetWindowsHookExW(
+        WH_MOUSE_LL,
+        Some(scroll_hook_proc),
+        util::get_instance_handle(),
+        0,
+    );
+    if !hook.is_null() {
+        SCROLL_HOOK.set(Some((hook, hwnd)));
+    }
+}
+
+/// Removes the hook installed by [`set_scroll_hook`], unless it was since installed for another window.
+unsafe fn remove_scroll_hook(hwnd: HWND) {
+    match SCROLL_HOOK.get() {
+        Some((hook, hook_hwnd)) if hook_hwnd == hwnd => {
+            UnhookWindowsHookEx(hook);
+            SCROLL_HOOK.set(None);
+        }
+        _ => {}
+    }
+}
+
+unsafe extern "system" fn scroll_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 && matches!(wparam as u32, WM_MOUSEWHEEL | WM_MOUSEHWHEEL) {
+        if let Some((_, hwnd)) = SCROLL_HOOK.get() {
+            let info = &*(lparam as *const MSLLHOOKSTRUCT);
+            PostMessageW(hwnd, WM_USER_SCROLL, wparam, info.mouseData as _);
+        }
+    }
+    CallNextHookEx(ptr::null_mut(), code, wparam, lparam)
+}
+
+/// Sends `value` to the window procedure of the tray icon, which takes it with [`take_sent`].
+///
+/// `SendMessageW` only returns once the message was handled, so the value is passed by pointer
+/// instead of being boxed, and it is simply dropped here if the message wasn't handled,
+/// e.g. because the window was destroyed before a [`TrayIconHandle`] was used.
+unsafe fn send_value<T>(hwnd: HWND, msg: u32, value: T) -> LRESULT {
+    let mut value = Some(value);
+    let result = SendMessageW(hwnd, msg, &mut value as *mut Option<T> as WPARAM, 0);
+    debug_assert!(
+        value.is_none() || IsWindow(hwnd) == FALSE,
+        "tray message {msg} wasn't handled"
+    );
+    result
+}
+
+/// Takes the value sent with [`send_value`], where `T` must be the type of the sent value.
+unsafe fn take_sent<T>(wparam: WPARAM) -> T {
+    (*(wparam as *mut Option<T>))
+        .take()
+        .expect("the sent value was already taken")
+}
+
+unsafe extern "system" fn tray_timer_proc(hwnd: HWND, msg: u32, wparam: WPARAM, _lparam: u32) {
+    // go through the window procedure so the message reaches
+    // our subclass as well when attached to a window we don't own
+    SendMessageW(hwnd, msg, wparam, 0);
+}
+
+/// Gets the cursor position in physical pixels.
+///
+/// The tray window is per-monitor v2 DPI aware so positions reported in its messages are
+/// already physical, but the thread may be DPI unaware when called from the app or from
+/// a window passed to [`TrayIcon::attach_to`], where the system would virtualize them.
+/// [`get_tray_rect`] and [`show_tray_menu`] switch the awareness the same way.
+unsafe fn get_cursor_pos(cursor: &mut POINT) -> i32 {
+    util::with_per_monitor_v2_dpi_awareness(|| GetCursorPos(cursor))
 }
 
 #[inline]
-unsafe fn show_tray_menu(hwnd: HWND, menu: HMENU, x: i32, y: i32) {
+unsafe fn show_tray_menu(
+    hwnd: HWND,
+    menu: HMENU,
+    x: i32,
+    y: i32,
+    set_foreground: bool,
+    alignment: MenuAlignment,
+    theme: &MenuTheme,
+) {
     // bring the hidden window to the foreground so the pop up menu
     // would automatically hide on click outside
-    SetForegroundWindow(hwnd);
-    TrackPopupMenu(
-        menu,
-        // align bottom / right, maybe we could expose this later..
-        TPM_BOTTOMALIGN | TPM_LEFTALIGN,
-        x,
-        y,
-        0,
-        hwnd,
-        std::ptr::null_mut(),
-    );
+    if set_foreground {
+        SetForegroundWindow(hwnd);
+    }
+    util::set_menu_color_scheme(hwnd, theme.color_scheme);
+    // `x` and `y` are in physical pixels, see `get_cursor_pos`
+    util::with_per_monitor_v2_dpi_awareness(|| {
+        let flags = menu_flags(alignment, theme);
+        TrackPopupMenu(menu, flags, x, y, 0, hwnd, std::ptr::null_mut())
+    });
+    // force a task switch to our window so the next time
+    // the menu is opened it isn't dismissed right away
+    PostMessageW(hwnd, WM_NULL, 0, 0);
+}
+
+/// Returns the `TrackPopupMenu` flags for the menu alignment and theme.
+fn menu_flags(alignment: MenuAlignment, theme: &MenuTheme) -> TRACK_POPUP_MENU_FLAGS {
+    let (vertical, horizontal) = match alignment {
+        MenuAlignment::TopLeft => (TPM_TOPALIGN, TPM_LEFTALIGN),
+        MenuAlignment::TopCenter => (TPM_TOPALIGN, TPM_CENTERALIGN),
+        MenuAlignment::TopRight => (TPM_TOPALIGN, TPM_RIGHTALIGN),
+        MenuAlignment::BottomLeft => (TPM_BOTTOMALIGN, TPM_LEFTALIGN),
+        MenuAlignment::BottomCenter => (TPM_BOTTOMALIGN, TPM_CENTERALIGN),
+        MenuAlignment::BottomRight => (TPM_BOTTOMALIGN, TPM_RIGHTALIGN),
+    };
+
+    if !theme.right_to_left {
+        return vertical | horizontal;
+    }
+    let horizontal = match horizontal {
+        TPM_LEFTALIGN => TPM_RIGHTALIGN,
+        TPM_RIGHTALIGN => TPM_LEFTALIGN,
+        centered => centered,
+    };
+    vertical | horizontal | TPM_LAYOUTRTL
 }
 
 #[inline]
 unsafe fn register_tray_icon(
     hwnd: HWND,
     tray_id: u32,
+    guid: Option<u128>,
     hicon: &Option<HICON>,
     tooltip: &Option<String>,
 ) -> bool {
@@ -535,15 +1721,13 @@ unsafe fn register_tray_icon(
     }
 
     if let Some(tooltip) = tooltip {
-        flags |= NIF_TIP;
-        let tip = util::encode_wide(tooltip);
-        #[allow(clippy::manual_memcpy)]
-        for i in 0..tip.len().min(128) {
-            sz_tip[i] = tip[i];
-        }
+        // the tooltip isn't shown for `NOTIFYICON_VERSION_4` icons without NIF_SHOWTIP
+        flags |= NIF_TIP | NIF_SHOWTIP;
+        copy_tooltip(&mut sz_tip, tooltip);
     }
 
     let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
         uFlags: flags,
         hWnd: hwnd,
         uID: tray_id,
@@ -552,31 +1736,328 @@ unsafe fn register_tray_icon(
         szTip: sz_tip,
         ..std::mem::zeroed()
     };
+    set_guid(&mut nid, guid);
+
+    if Shell_NotifyIconW(NIM_ADD, &mut nid as _) == TRUE {
+        return true;
+    }
+    // adding fails while an icon with the same GUID is still registered, e.g. when it
+    // was left behind by a crashed process, so remove that one and try again
+    guid.is_some()
+        && remove_tray_icon(hwnd, tray_id, guid).is_ok()
+        && Shell_NotifyIconW(NIM_ADD, &mut nid as _) == TRUE
+}
+
+/// Whether explorer created the taskbar, adding tray icons fails until it does.
+fn taskbar_exists() -> bool {
+    unsafe { !FindWindowW(w!("Shell_TrayWnd"), ptr::null()).is_null() }
+}
+
+/// Whether a tray icon with `guid` is registered with the shell, e.g. by another instance
+/// of the app, see [`TrayIconBuilder::with_guid`](crate::TrayIconBuilder::with_guid).
+///
+/// This probes the shell by adding a hidden tray icon with `guid` and removing it right away,
+/// so the result is only a snapshot and inherently racy:
+///
+/// - Another instance can add or remove its icon right after this returns, so don't rely on it
+///   alone to enforce a single instance.
+/// - While the probe icon exists, another instance adding its icon with `guid` fails, and if
+///   that instance removes the icon to add it again, this can remove that icon instead.
+///
+/// Also returns `true` if the GUID is registered for another executable path, since adding an
+/// icon with it fails as well, and `false` if the taskbar doesn't exist yet.
+pub fn guid_in_use(guid: u128) -> bool {
+    unsafe {
+        let hwnd = CreateWindowExW(
+            0,
+            w!("STATIC"),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            util::get_instance_handle(),
+            ptr::null(),
+        );
+        if hwnd.is_null() {
+            return false;
+        }
+
+        let mut nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+            uFlags: NIF_STATE,
+            hWnd: hwnd,
+            dwState: NIS_HIDDEN,
+            dwStateMask: NIS_HIDDEN,
+            ..std::mem::zeroed()
+        };
+        set_guid(&mut nid, Some(guid));
+        let added = Shell_NotifyIconW(NIM_ADD, &mut nid as _) == TRUE;
+        if added {
+            Shell_NotifyIconW(NIM_DELETE, &mut nid as _);
+        }
+        DestroyWindow(hwnd);
+
+        !added && taskbar_exists()
+    }
+}
+
+/// The shell calls made to register a tray icon, so registering can be simulated in tests.
+trait NotifyIconShell {
+    /// Adds the tray icon with `NIM_ADD`, returns whether it was added.
+    fn add(&mut self) -> bool;
+    /// Removes the tray icon with `NIM_DELETE`.
+    fn remove(&mut self);
+    /// Opts into `NOTIFYICON_VERSION_4`, returns whether the shell accepted it.
+    fn set_version(&mut self) -> bool;
+    /// Whether explorer created the taskbar, see [`taskbar_exists`].
+    fn taskbar_exists(&self) -> bool;
+}
+
+/// Registers tray icons with the actual shell.
+struct Shell<'a> {
+    hwnd: HWND,
+    tray_id: u32,
+    guid: Option<u128>,
+    hicon: &'a Option<HICON>,
+    tooltip: &'a Option<String>,
+}
+
+impl NotifyIconShell for Shell<'_> {
+    fn add(&mut self) -> bool {
+        unsafe { register_tray_icon(self.hwnd, self.tray_id, self.guid, self.hicon, self.tooltip) }
+    }
+
+    fn remove(&mut self) {
+        let _ = unsafe { remove_tray_icon(self.hwnd, self.tray_id, self.guid) };
+    }
+
+    fn set_version(&mut self) -> bool {
+        unsafe { set_tray_version(self.hwnd, self.tray_id, self.guid) }
+    }
+
+    fn taskbar_exists(&self) -> bool {
+        taskbar_exists()
+    }
+}
+
+/// How registering a new tray icon turned out, see [`register`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Registration {
+    /// The tray icon was added.
+    Added { version_4: bool },
+    /// The taskbar doesn't exist yet, the tray icon is added by [`reregister`]
+    /// once it broadcasts `TaskbarCreated`.
+    Deferred,
+    /// Adding the tray icon failed even though the taskbar exists.
+    Failed,
+}
+
+/// Adds a new tray icon, deferring it when explorer hasn't created the taskbar yet,
+/// e.g. right after logging in.
+fn register(shell: &mut impl NotifyIconShell) -> Registration {
+    if shell.add() {
+        Registration::Added {
+            version_4: shell.set_version(),
+        }
+    } else if shell.taskbar_exists() {
+        Registration::Failed
+    } else {
+        Registration::Deferred
+    }
+}
 
-    Shell_NotifyIconW(NIM_ADD, &mut nid as _) == TRUE
+/// Adds the tray icon again once the taskbar is created or restarted, returns whether
+/// it uses `NOTIFYICON_VERSION_4` if it was added.
+fn reregister(shell: &mut impl NotifyIconShell) -> Option<bool> {
+    // the icon is usually gone already when the taskbar restarts
+    shell.remove();
+    shell.add().then(|| shell.set_version())
+}
+
+/// Waits up to `timeout` for messages to arrive on the calling thread and dispatches them,
+/// so the tray icon keeps handling e.g. `TaskbarCreated` while the caller blocks.
+///
+/// A `WM_QUIT` message is posted again for the app's own message loop.
+pub(crate) fn wait_for_messages(timeout: Duration) {
+    unsafe {
+        MsgWaitForMultipleObjects(
+            0,
+            ptr::null(),
+            FALSE,
+            timeout.as_millis().try_into().unwrap_or(u32::MAX),
+            QS_ALLINPUT,
+        );
+
+        let mut msg: MSG = std::mem::zeroed();
+        while PeekMessageW(&mut msg, ptr::null_mut(), 0, 0, PM_REMOVE) != 0 {
+            if msg.message == WM_QUIT {
+                PostQuitMessage(msg.wParam as i32);
+                break;
+            }
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+/// Opts into `NOTIFYICON_VERSION_4` messages for the tray icon, which has to be done every time
+/// it is added, returning whether the shell supports them.
+unsafe fn set_tray_version(hwnd: HWND, tray_id: u32, guid: Option<u128>) -> bool {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+        hWnd: hwnd,
+        uID: tray_id,
+        Anonymous: NOTIFYICONDATAW_0 {
+            uVersion: NOTIFYICON_VERSION_4,
+        },
+        ..std::mem::zeroed()
+    };
+    set_guid(&mut nid, guid);
+
+    Shell_NotifyIconW(NIM_SETVERSION, &mut nid as _) == TRUE
+}
+
+/// Returns the keyboard focus to the notification area, after a menu opened with
+/// the keyboard is closed.
+unsafe fn set_tray_focus(hwnd: HWND, tray_id: u32, guid: Option<u128>) -> bool {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+        hWnd: hwnd,
+        uID: tray_id,
+        ..std::mem::zeroed()
+    };
+    set_guid(&mut nid, guid);
+
+    Shell_NotifyIconW(NIM_SETFOCUS, &mut nid as _) == TRUE
+}
+
+/// Identifies the tray icon by `guid` instead of its window and id if it has one,
+/// see [`TrayIconBuilder::with_guid`](crate::TrayIconBuilder::with_guid).
+fn set_guid(nid: &mut NOTIFYICONDATAW, guid: Option<u128>) {
+    if let Some(guid) = guid {
+        nid.uFlags |= NIF_GUID;
+        nid.guidItem = GUID::from_u128(guid);
+    }
 }
 
 #[inline]
-unsafe fn remove_tray_icon(hwnd: HWND, id: u32) {
+unsafe fn modify_tray_icon(
+    hwnd: HWND,
+    tray_id: u32,
+    guid: Option<u128>,
+    icon: Option<&Icon>,
+) -> bool {
     let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+        uFlags: NIF_ICON,
+        hWnd: hwnd,
+        uID: tray_id,
+        ..std::mem::zeroed()
+    };
+
+    if let Some(hicon) = icon.map(|i| i.inner.as_raw_handle()) {
+        nid.hIcon = hicon;
+    }
+    set_guid(&mut nid, guid);
+
+    Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == TRUE
+}
+
+#[inline]
+unsafe fn modify_tray_tooltip(
+    hwnd: HWND,
+    tray_id: u32,
+    guid: Option<u128>,
+    tooltip: Option<&str>,
+) -> bool {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+        uFlags: NIF_TIP | NIF_SHOWTIP,
+        hWnd: hwnd,
+        uID: tray_id,
+        ..std::mem::zeroed()
+    };
+
+    if let Some(tooltip) = tooltip {
+        copy_tooltip(&mut nid.szTip, tooltip);
+    }
+    set_guid(&mut nid, guid);
+
+    Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == TRUE
+}
+
+/// The maximum length of a tooltip in UTF-16 code units, without the nul terminator.
+const MAX_TOOLTIP_LEN: usize = 127;
+
+/// Returns [`crate::Error::TooltipTooLong`] if the tooltip would be truncated by [`copy_tooltip`].
+pub(crate) fn check_tooltip_len(tooltip: &str) -> crate::Result<()> {
+    let len = encode_tooltip(tooltip).len() - 1;
+    if len > MAX_TOOLTIP_LEN {
+        return Err(crate::Error::TooltipTooLong {
+            len,
+            max: MAX_TOOLTIP_LEN,
+        });
+    }
+    Ok(())
+}
+
+/// Copies the encoded tooltip into `dst`, see [`copy_wide`].
+fn copy_tooltip(dst: &mut [u16; MAX_TOOLTIP_LEN + 1], tooltip: &str) {
+    copy_wide(dst, &encode_tooltip(tooltip));
+}
+
+/// Copies the nul-terminated wide string `src` into `dst`, truncating it to fit
+/// with its nul terminator without splitting a surrogate pair.
+fn copy_wide(dst: &mut [u16], src: &[u16]) {
+    let mut len = (src.len() - 1).min(dst.len() - 1);
+    if len > 0 && len < src.len() - 1 && (0xD800..0xDC00).contains(&src[len - 1]) {
+        len -= 1;
+    }
+    dst[..len].copy_from_slice(&src[..len]);
+    dst[len] = 0;
+}
+
+/// Encodes the tooltip into a nul-terminated wide string, normalizing it
+/// to NFC first when the `unicode-normalization` feature is enabled.
+fn encode_tooltip(tooltip: &str) -> Vec<u16> {
+    #[cfg(feature = "unicode-normalization")]
+    {
+        use unicode_normalization::UnicodeNormalization;
+        util::encode_wide(tooltip.nfc().collect::<String>())
+    }
+    #[cfg(not(feature = "unicode-normalization"))]
+    util::encode_wide(tooltip)
+}
+
+#[inline]
+unsafe fn remove_tray_icon(hwnd: HWND, id: u32, guid: Option<u128>) -> crate::Result<()> {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
         uFlags: NIF_ICON,
         hWnd: hwnd,
         uID: id,
         ..std::mem::zeroed()
     };
+    set_guid(&mut nid, guid);
 
     if Shell_NotifyIconW(NIM_DELETE, &mut nid as _) == FALSE {
-        eprintln!("Error removing system tray icon");
+        return Err(tray_error("NIM_DELETE", id));
     }
+    Ok(())
 }
 
 #[inline]
-fn get_tray_rect(id: u32, hwnd: HWND) -> Option<RECT> {
+fn get_tray_rect(id: u32, hwnd: HWND, guid: Option<u128>) -> Option<RECT> {
     let nid = NOTIFYICONIDENTIFIER {
         hWnd: hwnd,
         cbSize: std::mem::size_of::<NOTIFYICONIDENTIFIER>() as _,
         uID: id,
-        ..unsafe { std::mem::zeroed() }
+        // a zeroed GUID identifies the icon by its window and id instead
+        guidItem: GUID::from_u128(guid.unwrap_or(0)),
     };
 
     let mut rect = RECT {
@@ -585,7 +2066,11 @@ fn get_tray_rect(id: u32, hwnd: HWND) -> Option<RECT> {
         right: 0,
         top: 0,
     };
-    if unsafe { Shell_NotifyIconGetRect(&nid, &mut rect) } == S_OK {
+    // see `get_cursor_pos`
+    let result = util::with_per_monitor_v2_dpi_awareness(|| unsafe {
+        Shell_NotifyIconGetRect(&nid, &mut rect)
+    });
+    if result == S_OK {
         Some(rect)
     } else {
         None
@@ -603,3 +2088,207 @@ impl From<RECT> for Rect {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A shell whose taskbar doesn't exist until `taskbar` is set.
+    #[derive(Default)]
+    struct FakeShell {
+        taskbar: bool,
+        icons: usize,
+    }
+
+    impl NotifyIconShell for FakeShell {
+        fn add(&mut self) -> bool {
+            if self.taskbar {
+                self.icons += 1;
+            }
+            self.taskbar
+        }
+
+        fn remove(&mut self) {
+            self.icons = self.icons.saturating_sub(1);
+        }
+
+        fn set_version(&mut self) -> bool {
+            self.taskbar
+        }
+
+        fn taskbar_exists(&self) -> bool {
+            self.taskbar
+        }
+    }
+
+    #[test]
+    fn registration_is_deferred_until_the_taskbar_is_created() {
+        let mut shell = FakeShell::default();
+        assert_eq!(register(&mut shell), Registration::Deferred);
+        assert_eq!(shell.icons, 0);
+
+        // explorer starts and broadcasts `TaskbarCreated`
+        shell.taskbar = true;
+        assert_eq!(reregister(&mut shell), Some(true));
+        assert_eq!(shell.icons, 1);
+
+        // and again when it restarts, without adding the icon twice
+        assert_eq!(reregister(&mut shell), Some(true));
+        assert_eq!(shell.icons, 1);
+    }
+
+    #[test]
+    fn registration_fails_when_the_taskbar_exists() {
+        struct RejectingShell;
+
+        impl NotifyIconShell for RejectingShell {
+            fn add(&mut self) -> bool {
+                false
+            }
+            fn remove(&mut self) {}
+            fn set_version(&mut self) -> bool {
+                unreachable!("the icon was never added")
+            }
+            fn taskbar_exists(&self) -> bool {
+                true
+            }
+        }
+
+        assert_eq!(register(&mut RejectingShell), Registration::Failed);
+        assert_eq!(reregister(&mut RejectingShell), None);
+    }
+
+    #[test]
+    fn window_classes_are_registered_once() {
+        use windows_sys::Win32::UI::WindowsAndMessaging::GetClassInfoW;
+
+        let name = TRAY_CLASS_NAME.as_ref().unwrap();
+        assert!(String::from_utf16_lossy(name).contains(env!("CARGO_PKG_VERSION")));
+        unsafe {
+            let hinstance = util::get_instance_handle();
+            let mut class = std::mem::zeroed();
+            for class_name in [&TRAY_CLASS_NAME, &DIALOG_OWNER_CLASS_NAME] {
+                let name = window_class(class_name).unwrap();
+                assert_ne!(GetClassInfoW(hinstance, name, &mut class), 0);
+                // registering a class again fails until it is unregistered
+                let wnd_class = WNDCLASSW {
+                    lpfnWndProc: Some(DefWindowProcW),
+                    lpszClassName: name,
+                    hInstance: hinstance,
+                    ..std::mem::zeroed()
+                };
+                assert_eq!(RegisterClassW(&wnd_class), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn get_cursor_pos_restores_dpi_awareness() {
+        use windows_sys::Win32::UI::HiDpi::{
+            AreDpiAwarenessContextsEqual, GetThreadDpiAwarenessContext,
+        };
+
+        unsafe {
+            let before = GetThreadDpiAwarenessContext();
+            let mut cursor = POINT { x: 0, y: 0 };
+            get_cursor_pos(&mut cursor);
+            assert_ne!(
+                AreDpiAwarenessContextsEqual(before, GetThreadDpiAwarenessContext()),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn take_sent_moves_the_value_out() {
+        let value = Rc::new(());
+        let mut sent = Some(value.clone());
+        let taken: Rc<()> = unsafe { take_sent(&mut sent as *mut Option<Rc<()>> as WPARAM) };
+        assert!(sent.is_none());
+        drop(taken);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn get_tray_rect_is_none_for_unregistered_icon() {
+        // the shell fails to locate an icon that was never added, instead of returning a zeroed rect
+        assert!(get_tray_rect(u32::MAX, std::ptr::null_mut(), None).is_none());
+    }
+
+    #[test]
+    fn tray_error_includes_operation() {
+        use std::error::Error;
+
+        let error = std::io::Error::other(TrayOperationError {
+            operation: "NIM_ADD",
+            tray_id: 3,
+            source: std::io::Error::from_raw_os_error(5),
+        });
+        assert!(error.to_string().starts_with("failed NIM_ADD for icon 3: "));
+        assert_eq!(
+            error
+                .source()
+                .and_then(|e| e.downcast_ref::<std::io::Error>())
+                .and_then(|e| e.raw_os_error()),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn copy_tooltip_truncates_to_fit() {
+        let mut dst = [1; 128];
+        copy_tooltip(&mut dst, "tray");
+        assert_eq!(
+            &dst[..5],
+            &[b't' as u16, b'r' as u16, b'a' as u16, b'y' as u16, 0]
+        );
+
+        copy_tooltip(&mut dst, &"a".repeat(200));
+        assert_eq!(dst[126], b'a' as u16);
+        assert_eq!(dst[127], 0);
+
+        // don't split the surrogate pair ending at the 128th unit
+        copy_tooltip(&mut dst, &format!("{}😀", "a".repeat(126)));
+        assert_eq!(dst[125], b'a' as u16);
+        assert_eq!(dst[126], 0);
+
+        // a surrogate pair ending at the 127th unit still fits
+        let tooltip = format!("{}😀", "a".repeat(125));
+        copy_tooltip(&mut dst, &tooltip);
+        assert_eq!(dst[127], 0);
+
+        let len = dst.iter().position(|&c| c == 0).unwrap();
+        assert_eq!(String::from_utf16(&dst[..len]).unwrap(), tooltip);
+    }
+
+    #[test]
+    fn menu_flags_mirror_right_to_left_alignment() {
+        let ltr = MenuTheme::default();
+        let rtl = MenuTheme::default().with_right_to_left(true);
+        assert_eq!(
+            menu_flags(MenuAlignment::default(), &ltr),
+            TPM_BOTTOMALIGN | TPM_LEFTALIGN
+        );
+        assert_eq!(
+            menu_flags(MenuAlignment::default(), &rtl),
+            TPM_BOTTOMALIGN | TPM_RIGHTALIGN | TPM_LAYOUTRTL
+        );
+        assert_eq!(
+            menu_flags(MenuAlignment::TopCenter, &rtl),
+            TPM_TOPALIGN | TPM_CENTERALIGN | TPM_LAYOUTRTL
+        );
+        assert_eq!(
+            menu_flags(MenuAlignment::TopRight, &ltr),
+            TPM_TOPALIGN | TPM_RIGHTALIGN
+        );
+    }
+
+    #[test]
+    fn check_tooltip_len_rejects_truncated_tooltips() {
+        assert!(check_tooltip_len(&"a".repeat(127)).is_ok());
+        assert!(matches!(
+            check_tooltip_len(&format!("{}😀", "a".repeat(126))),
+            Err(crate::Error::TooltipTooLong { len: 128, max: 127 })
+        ));
+    }
+}
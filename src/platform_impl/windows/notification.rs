@@ -0,0 +1,107 @@
+// Copyright 2022-2022 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use windows_sys::Win32::{
+    Foundation::HWND,
+    UI::Shell::{
+        NIF_INFO, NIIF_ERROR, NIIF_INFO, NIIF_LARGE_ICON, NIIF_NONE, NIIF_USER, NIIF_WARNING,
+        NOTIFYICONDATAW, NOTIFY_ICON_INFOTIP_FLAGS,
+    },
+};
+
+use super::{copy_wide, util};
+
+/// The icon shown in a balloon notification, see [`TrayIcon::show_notification`](crate::TrayIcon::show_notification).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NotificationIcon {
+    /// No icon.
+    #[default]
+    None,
+    /// The system information icon.
+    Info,
+    /// The system warning icon.
+    Warning,
+    /// The system error icon.
+    Error,
+    /// The tray icon.
+    User,
+    /// A large version of the tray icon.
+    LargeUser,
+}
+
+impl NotificationIcon {
+    fn flags(self) -> NOTIFY_ICON_INFOTIP_FLAGS {
+        match self {
+            NotificationIcon::None => NIIF_NONE,
+            NotificationIcon::Info => NIIF_INFO,
+            NotificationIcon::Warning => NIIF_WARNING,
+            NotificationIcon::Error => NIIF_ERROR,
+            NotificationIcon::User => NIIF_USER,
+            NotificationIcon::LargeUser => NIIF_USER | NIIF_LARGE_ICON,
+        }
+    }
+}
+
+/// Builds the `NIM_MODIFY` data to show a balloon notification for the tray icon,
+/// truncating `title` to 63 and `body` to 255 UTF-16 code units.
+pub(crate) fn notification_data(
+    hwnd: HWND,
+    id: u32,
+    title: &str,
+    body: &str,
+    icon: NotificationIcon,
+) -> NOTIFYICONDATAW {
+    let mut nid = NOTIFYICONDATAW {
+        // the balloon fields are only read when the size includes them
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+        uFlags: NIF_INFO,
+        hWnd: hwnd,
+        uID: id,
+        dwInfoFlags: icon.flags(),
+        ..unsafe { std::mem::zeroed() }
+    };
+    copy_wide(&mut nid.szInfoTitle, &util::encode_wide(title));
+    copy_wide(&mut nid.szInfo, &util::encode_wide(body));
+    nid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_data_is_populated() {
+        let nid = notification_data(
+            std::ptr::null_mut(),
+            7,
+            "Title",
+            "Body",
+            NotificationIcon::Info,
+        );
+        assert_eq!(nid.cbSize as usize, std::mem::size_of::<NOTIFYICONDATAW>());
+        assert_eq!(nid.uFlags, NIF_INFO);
+        assert_eq!(nid.uID, 7);
+        assert_eq!(nid.dwInfoFlags, NIIF_INFO);
+        assert_eq!(&nid.szInfoTitle[..6], util::encode_wide("Title").as_slice());
+        assert_eq!(&nid.szInfo[..5], util::encode_wide("Body").as_slice());
+
+        let nid = notification_data(
+            std::ptr::null_mut(),
+            7,
+            &"t".repeat(100),
+            &"b".repeat(300),
+            NotificationIcon::LargeUser,
+        );
+        assert_eq!(nid.dwInfoFlags, NIIF_USER | NIIF_LARGE_ICON);
+        assert_eq!(nid.szInfoTitle[62], b't' as u16);
+        assert_eq!(nid.szInfoTitle[63], 0);
+        assert_eq!(nid.szInfo[254], b'b' as u16);
+        assert_eq!(nid.szInfo[255], 0);
+
+        let nid = notification_data(std::ptr::null_mut(), 7, "", "", NotificationIcon::None);
+        assert_eq!(nid.dwInfoFlags, NIIF_NONE);
+        assert_eq!(nid.szInfoTitle[0], 0);
+    }
+}
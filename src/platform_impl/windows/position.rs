@@ -0,0 +1,347 @@
+// Copyright 2022-2022 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use once_cell::sync::Lazy;
+use windows_sys::{
+    core::HRESULT,
+    s, w,
+    Win32::{
+        Foundation::{POINT, RECT, S_OK},
+        Graphics::Gdi::{
+            GetMonitorInfoW, MonitorFromPoint, MonitorFromRect, HMONITOR, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST,
+        },
+        System::LibraryLoader::{GetProcAddress, LoadLibraryW},
+        UI::{
+            HiDpi::{MDT_EFFECTIVE_DPI, MONITOR_DPI_TYPE},
+            Shell::{
+                SHAppBarMessage, ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_GETSTATE,
+                ABM_GETTASKBARPOS, ABS_AUTOHIDE, APPBARDATA,
+            },
+            WindowsAndMessaging::{
+                GetWindowRect, SetWindowPos, MONITORINFOF_PRIMARY, SWP_NOACTIVATE, SWP_NOSIZE,
+                SWP_NOZORDER,
+            },
+        },
+    },
+};
+
+use super::util;
+use crate::{dpi::PhysicalPosition, Rect};
+
+/// Describes the screen edge the taskbar is docked to, see [`taskbar_position`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskbarEdge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+/// Moves the window `hwnd` next to the tray icon at `rect`, on the side facing away from
+/// the taskbar `edge`, so it appears just above the icon when the taskbar is at the bottom,
+/// just below it when the taskbar is at the top and beside it otherwise.
+///
+/// The window is centered on the icon and clamped to the work area of the monitor
+/// the icon is on.
+///
+/// `rect` is usually retrieved from [`TrayIcon::rect`](crate::TrayIcon::rect)
+/// or from a [`TrayIconEvent`](crate::TrayIconEvent).
+pub fn position_window_near(hwnd: isize, rect: &Rect, edge: TaskbarEdge) -> crate::Result<()> {
+    let hwnd = hwnd as _;
+
+    let icon = RECT {
+        left: rect.position.x as i32,
+        top: rect.position.y as i32,
+        right: rect.position.x as i32 + rect.size.width as i32,
+        bottom: rect.position.y as i32 + rect.size.height as i32,
+    };
+
+    unsafe {
+        let mut window = std::mem::zeroed();
+        if GetWindowRect(hwnd, &mut window) == 0 {
+            return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+        }
+
+        let center = POINT {
+            x: icon.left + (icon.right - icon.left) / 2,
+            y: icon.top + (icon.bottom - icon.top) / 2,
+        };
+        let hmonitor = MonitorFromPoint(center, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as _,
+            ..std::mem::zeroed()
+        };
+        if GetMonitorInfoW(hmonitor, &mut info) == 0 {
+            return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+        }
+
+        let size = (window.right - window.left, window.bottom - window.top);
+        let (x, y) = anchor_position(icon, size, info.rcWork, edge);
+
+        if SetWindowPos(
+            hwnd,
+            std::ptr::null_mut(),
+            x,
+            y,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        ) == 0
+        {
+            return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `position` lies on the primary monitor, or the monitor nearest to it.
+pub(crate) fn is_on_primary_monitor(position: PhysicalPosition<f64>) -> bool {
+    let point = POINT {
+        x: position.x as i32,
+        y: position.y as i32,
+    };
+
+    // `position` is in physical pixels
+    util::with_per_monitor_v2_dpi_awareness(|| unsafe {
+        let hmonitor = MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as _,
+            ..std::mem::zeroed()
+        };
+        GetMonitorInfoW(hmonitor, &mut info) != 0 && info.dwFlags & MONITORINFOF_PRIMARY != 0
+    })
+}
+
+/// Whether the taskbar is set to auto-hide.
+///
+/// While an auto-hidden taskbar is slid out of view, the tray icon is off-screen and
+/// [`TrayIcon::rect`](crate::TrayIcon::rect) returns where it is shown once the taskbar is revealed.
+pub fn taskbar_autohidden() -> bool {
+    let mut data = APPBARDATA {
+        cbSize: std::mem::size_of::<APPBARDATA>() as _,
+        ..unsafe { std::mem::zeroed() }
+    };
+    unsafe { SHAppBarMessage(ABM_GETSTATE, &mut data) as u32 & ABS_AUTOHIDE != 0 }
+}
+
+/// Returns the screen edge the taskbar is docked to and its bounds in physical pixels.
+///
+/// Tray icons are only shown on the taskbar of the primary monitor, which is the one returned.
+/// Together with [`TrayIcon::rect`](crate::TrayIcon::rect), this allows anchoring a popup on
+/// the side of the tray icon facing away from the taskbar, see [`position_window_near`].
+pub fn taskbar_position() -> Option<(TaskbarEdge, Rect)> {
+    let mut data = APPBARDATA {
+        cbSize: std::mem::size_of::<APPBARDATA>() as _,
+        ..unsafe { std::mem::zeroed() }
+    };
+    let found = util::with_per_monitor_v2_dpi_awareness(|| unsafe {
+        SHAppBarMessage(ABM_GETTASKBARPOS, &mut data) != 0
+    });
+    if !found {
+        return None;
+    }
+    Some((taskbar_edge(data.uEdge)?, data.rc.into()))
+}
+
+/// Maps one of the `ABE_*` values to a [`TaskbarEdge`].
+fn taskbar_edge(edge: u32) -> Option<TaskbarEdge> {
+    match edge {
+        ABE_LEFT => Some(TaskbarEdge::Left),
+        ABE_TOP => Some(TaskbarEdge::Top),
+        ABE_RIGHT => Some(TaskbarEdge::Right),
+        ABE_BOTTOM => Some(TaskbarEdge::Bottom),
+        _ => None,
+    }
+}
+
+type GetDpiForMonitor =
+    unsafe extern "system" fn(HMONITOR, MONITOR_DPI_TYPE, *mut u32, *mut u32) -> HRESULT;
+
+// loaded dynamically since it is only available on Windows 8.1 and later
+static GET_DPI_FOR_MONITOR: Lazy<Option<GetDpiForMonitor>> = Lazy::new(|| unsafe {
+    let shcore = LoadLibraryW(w!("shcore.dll"));
+    if shcore.is_null() {
+        return None;
+    }
+    GetProcAddress(shcore, s!("GetDpiForMonitor"))
+        .map(|f| std::mem::transmute::<_, GetDpiForMonitor>(f))
+});
+
+/// Returns the DPI of the monitor nearest to `rect`, or the system DPI on systems
+/// without per-monitor DPI.
+pub(crate) fn monitor_dpi(rect: RECT) -> u32 {
+    let Some(get_dpi_for_monitor) = *GET_DPI_FOR_MONITOR else {
+        return super::system_dpi();
+    };
+
+    // `rect` is in physical pixels, see `TrayIcon::rect`
+    util::with_per_monitor_v2_dpi_awareness(|| unsafe {
+        let hmonitor = MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST);
+        let (mut dpi_x, mut dpi_y) = (0, 0);
+        if get_dpi_for_monitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) == S_OK {
+            dpi_x
+        } else {
+            super::system_dpi()
+        }
+    })
+}
+
+/// Moves `rect` inside the bounds of the monitor nearest to it, approximating the position
+/// of a tray icon on an auto-hidden taskbar once it is revealed.
+pub(crate) fn clamp_to_monitor(rect: RECT) -> RECT {
+    // `rect` is in physical pixels, see `TrayIcon::rect`
+    util::with_per_monitor_v2_dpi_awareness(|| unsafe {
+        let hmonitor = MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as _,
+            ..std::mem::zeroed()
+        };
+        if GetMonitorInfoW(hmonitor, &mut info) == 0 {
+            return rect;
+        }
+        clamp_rect(rect, info.rcMonitor)
+    })
+}
+
+/// Moves `position`, in physical pixels, inside the work area of the monitor `hmonitor`.
+pub(crate) fn clamp_to_work_area(
+    position: PhysicalPosition<f64>,
+    hmonitor: HMONITOR,
+) -> crate::Result<PhysicalPosition<f64>> {
+    util::with_per_monitor_v2_dpi_awareness(|| unsafe {
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as _,
+            ..std::mem::zeroed()
+        };
+        if GetMonitorInfoW(hmonitor, &mut info) == 0 {
+            return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+        }
+        let point = POINT {
+            x: position.x.round() as i32,
+            y: position.y.round() as i32,
+        };
+        let point = clamp_point(point, info.rcWork);
+        Ok(PhysicalPosition::new(point.x as f64, point.y as f64))
+    })
+}
+
+/// Moves `point` inside `bounds`, whose right and bottom edges are exclusive.
+fn clamp_point(point: POINT, bounds: RECT) -> POINT {
+    POINT {
+        x: point.x.min(bounds.right - 1).max(bounds.left),
+        y: point.y.min(bounds.bottom - 1).max(bounds.top),
+    }
+}
+
+/// Moves `rect` inside `bounds` without resizing it.
+fn clamp_rect(rect: RECT, bounds: RECT) -> RECT {
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+    let left = rect.left.min(bounds.right - width).max(bounds.left);
+    let top = rect.top.min(bounds.bottom - height).max(bounds.top);
+
+    RECT {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+    }
+}
+
+/// Computes the top-left position of a window of `size` anchored to the `icon` rect,
+/// clamped to the `work` area.
+fn anchor_position(icon: RECT, size: (i32, i32), work: RECT, edge: TaskbarEdge) -> (i32, i32) {
+    let (width, height) = size;
+    let center_x = icon.left + (icon.right - icon.left) / 2;
+    let center_y = icon.top + (icon.bottom - icon.top) / 2;
+
+    let (x, y) = match edge {
+        TaskbarEdge::Bottom => (center_x - width / 2, icon.top - height),
+        TaskbarEdge::Top => (center_x - width / 2, icon.bottom),
+        TaskbarEdge::Left => (icon.right, center_y - height / 2),
+        TaskbarEdge::Right => (icon.left - width, center_y - height / 2),
+    };
+
+    // prefer keeping the top-left corner visible if the window is larger than the work area
+    let x = x.min(work.right - width).max(work.left);
+    let y = y.min(work.bottom - height).max(work.top);
+
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn clamps_hidden_rect_into_monitor() {
+        let monitor = rect(0, 0, 1920, 1080);
+
+        // taskbar hidden below the bottom edge
+        let clamped = clamp_rect(rect(1800, 1078, 1832, 1110), monitor);
+        assert_eq!(
+            (clamped.left, clamped.top, clamped.right, clamped.bottom),
+            (1800, 1048, 1832, 1080)
+        );
+
+        // already visible
+        let visible = clamp_rect(rect(1800, 1045, 1832, 1077), monitor);
+        assert_eq!(
+            (visible.left, visible.top, visible.right, visible.bottom),
+            (1800, 1045, 1832, 1077)
+        );
+    }
+
+    #[test]
+    fn clamps_point_into_work_area() {
+        let work = rect(1920, 0, 3840, 1040);
+
+        let clamped = clamp_point(POINT { x: 100, y: 1070 }, work);
+        assert_eq!((clamped.x, clamped.y), (1920, 1039));
+
+        let inside = clamp_point(POINT { x: 2000, y: 500 }, work);
+        assert_eq!((inside.x, inside.y), (2000, 500));
+    }
+
+    #[test]
+    fn anchors_and_clamps_to_work_area() {
+        let work = rect(0, 0, 1920, 1040);
+        let icon = rect(1800, 1045, 1832, 1077);
+
+        assert_eq!(
+            anchor_position(icon, (200, 300), work, TaskbarEdge::Bottom),
+            (1716, 740)
+        );
+        // clamped to the right edge of the work area
+        assert_eq!(
+            anchor_position(
+                rect(1900, 1045, 1920, 1077),
+                (200, 300),
+                work,
+                TaskbarEdge::Bottom
+            ),
+            (1720, 740)
+        );
+        assert_eq!(
+            anchor_position(
+                rect(100, 0, 132, 32),
+                (200, 300),
+                rect(0, 40, 1920, 1080),
+                TaskbarEdge::Top
+            ),
+            (16, 40)
+        );
+    }
+}
@@ -4,11 +4,22 @@
 
 use std::ops::{Deref, DerefMut};
 
-use windows_sys::Win32::{
-    Foundation::HWND,
-    UI::WindowsAndMessaging::{ACCEL, WINDOW_LONG_PTR_INDEX},
+use once_cell::sync::Lazy;
+use windows_sys::{
+    core::PCSTR,
+    s, w,
+    Win32::{
+        Foundation::{BOOL, HWND},
+        System::LibraryLoader::{GetModuleHandleW, GetProcAddress, LoadLibraryW},
+        UI::{
+            HiDpi::{DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2},
+            WindowsAndMessaging::{ACCEL, WINDOW_LONG_PTR_INDEX},
+        },
+    },
 };
 
+use crate::MenuColorScheme;
+
 pub fn encode_wide<S: AsRef<std::ffi::OsStr>>(string: S) -> Vec<u16> {
     std::os::windows::prelude::OsStrExt::encode_wide(string.as_ref())
         .chain(std::iter::once(0))
@@ -87,3 +98,88 @@ pub unsafe fn set_window_long(
             as isize
     };
 }
+
+type SetThreadDpiAwarenessContext =
+    unsafe extern "system" fn(DPI_AWARENESS_CONTEXT) -> DPI_AWARENESS_CONTEXT;
+
+// loaded dynamically since it is only available on Windows 10 1607 and later
+static SET_THREAD_DPI_AWARENESS_CONTEXT: Lazy<Option<SetThreadDpiAwarenessContext>> =
+    Lazy::new(|| unsafe {
+        let user32 = GetModuleHandleW(w!("user32.dll"));
+        if user32.is_null() {
+            return None;
+        }
+        GetProcAddress(user32, s!("SetThreadDpiAwarenessContext"))
+            .map(|f| std::mem::transmute::<_, SetThreadDpiAwarenessContext>(f))
+    });
+
+/// Runs `f` with the calling thread's DPI awareness set to per-monitor v2,
+/// restoring the previous awareness afterwards.
+///
+/// `f` is called as is on systems that don't support per-monitor v2.
+pub fn with_per_monitor_v2_dpi_awareness<T>(f: impl FnOnce() -> T) -> T {
+    let Some(set_thread_dpi_awareness_context) = *SET_THREAD_DPI_AWARENESS_CONTEXT else {
+        return f();
+    };
+
+    unsafe {
+        let previous = set_thread_dpi_awareness_context(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+        let result = f();
+        if !previous.is_null() {
+            set_thread_dpi_awareness_context(previous);
+        }
+        result
+    }
+}
+
+type SetPreferredAppMode = unsafe extern "system" fn(i32) -> i32;
+type AllowDarkModeForWindow = unsafe extern "system" fn(HWND, BOOL) -> BOOL;
+type FlushMenuThemes = unsafe extern "system" fn();
+
+struct DarkModeFns {
+    set_preferred_app_mode: SetPreferredAppMode,
+    allow_dark_mode_for_window: AllowDarkModeForWindow,
+    flush_menu_themes: FlushMenuThemes,
+}
+
+// uxtheme only exports these by ordinal, and ordinal 135 is only `SetPreferredAppMode`
+// on Windows 10 1903 and later
+static DARK_MODE_FNS: Lazy<Option<DarkModeFns>> = Lazy::new(|| unsafe {
+    if !super::shell_capabilities().dark_menus {
+        return None;
+    }
+    let uxtheme = LoadLibraryW(w!("uxtheme.dll"));
+    if uxtheme.is_null() {
+        return None;
+    }
+    let ordinal = |ordinal: usize| GetProcAddress(uxtheme, ordinal as PCSTR);
+    Some(DarkModeFns {
+        allow_dark_mode_for_window: ordinal(133)
+            .map(|f| std::mem::transmute::<_, AllowDarkModeForWindow>(f))?,
+        set_preferred_app_mode: ordinal(135)
+            .map(|f| std::mem::transmute::<_, SetPreferredAppMode>(f))?,
+        flush_menu_themes: ordinal(136).map(|f| std::mem::transmute::<_, FlushMenuThemes>(f))?,
+    })
+});
+
+/// Applies `color_scheme` to the menus of `hwnd`, see [`MenuColorScheme`].
+///
+/// Does nothing for [`MenuColorScheme::Default`] and on systems without dark menus.
+pub fn set_menu_color_scheme(hwnd: HWND, color_scheme: MenuColorScheme) {
+    // `PreferredAppMode` values
+    let mode = match color_scheme {
+        MenuColorScheme::Default => return,
+        MenuColorScheme::System => 1,
+        MenuColorScheme::Dark => 2,
+        MenuColorScheme::Light => 3,
+    };
+    let Some(fns) = &*DARK_MODE_FNS else {
+        return;
+    };
+
+    unsafe {
+        (fns.allow_dark_mode_for_window)(hwnd, (color_scheme != MenuColorScheme::Light).into());
+        (fns.set_preferred_app_mode)(mode);
+        (fns.flush_menu_themes)();
+    }
+}